@@ -1,7 +1,145 @@
 use std::env;
 use std::fs::File;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+use embedded_graphics::pixelcolor::{Rgb888, RgbColor};
+use embedded_graphics::prelude::*;
+use tinybmp::Bmp;
+
+const ASSET_DIR: &str = "assets";
+const MAX_WIDTH: u32 = 128;
+const MAX_HEIGHT: u32 = 64;
+
+// Constant name and source image, everything here ends up in `sprites.rs`
+// as a 1-bit packed `ImageRaw` plus its `_WIDTH` and `_HEIGHT` in pixels.
+const SPRITES: &[(&str, &str)] = &[
+    ("PUDGE_BODY", "PudgeBody.bmp"),
+    ("PUDGE_THROW", "PudgeThrow.bmp"),
+    ("PUDGE_REEL", "PudgeReel.bmp"),
+    ("PUDGE_HOOK", "PudgeHook.bmp"),
+    ("PUDGE_HOOK_REELING", "PudgeHookReeling.bmp"),
+    ("PUDGE_CLEAVER", "PudgeCleaver.bmp"),
+    ("CREEP", "RadiantCreep.png"),
+    ("CREEP_STEP", "RadiantCreepStep.bmp"),
+    // Dire creeps still share the Radiant art and size
+    ("DIRE_CREEP", "RadiantCreep.png"),
+    ("SPLASH", "Splash.png"),
+];
+
+struct Sprite {
+    width: u32,
+    height: u32,
+    // Row-major, true for a lit pixel
+    pixels: Vec<bool>,
+}
+
+impl Sprite {
+    fn pack(&self) -> Vec<u8> {
+        let row_bytes = self.width.div_ceil(8) as usize;
+        let mut packed = vec![0u8; row_bytes * self.height as usize];
+        for y in 0..self.height as usize {
+            for x in 0..self.width as usize {
+                if self.pixels[y * self.width as usize + x] {
+                    packed[y * row_bytes + x / 8] |= 0x80 >> (x % 8);
+                }
+            }
+        }
+        packed
+    }
+}
+
+fn is_lit(luma: u32, alpha: u8) -> bool {
+    alpha > 127 && luma > 127
+}
+
+fn load_bmp(path: &Path) -> Result<Sprite, String> {
+    let data = std::fs::read(path).map_err(|e| e.to_string())?;
+    let bmp = Bmp::<Rgb888>::from_slice(&data).map_err(|e| format!("{:?}", e))?;
+    let Size { width, height } = bmp.size();
+    let mut pixels = vec![false; (width * height) as usize];
+    for Pixel(point, color) in bmp.pixels() {
+        let luma = (color.r() as u32 + color.g() as u32 + color.b() as u32) / 3;
+        pixels[point.y as usize * width as usize + point.x as usize] = is_lit(luma, 255);
+    }
+    Ok(Sprite {
+        width,
+        height,
+        pixels,
+    })
+}
+
+fn load_png(path: &Path) -> Result<Sprite, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let mut decoder = png::Decoder::new(file);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|e| e.to_string())?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).map_err(|e| e.to_string())?;
+    let channels = info.color_type.samples();
+    let pixels = buf[..info.buffer_size()]
+        .chunks(channels)
+        .map(|px| match info.color_type {
+            png::ColorType::Grayscale => is_lit(px[0].into(), 255),
+            png::ColorType::GrayscaleAlpha => is_lit(px[0].into(), px[1]),
+            png::ColorType::Rgb => is_lit(px.iter().map(|c| *c as u32).sum::<u32>() / 3, 255),
+            png::ColorType::Rgba => {
+                is_lit(px[..3].iter().map(|c| *c as u32).sum::<u32>() / 3, px[3])
+            }
+            png::ColorType::Indexed => unreachable!("palette is expanded by the decoder"),
+        })
+        .collect();
+    Ok(Sprite {
+        width: info.width,
+        height: info.height,
+        pixels,
+    })
+}
+
+fn load_sprite(path: &Path) -> Result<Sprite, String> {
+    let sprite = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("bmp") => load_bmp(path)?,
+        Some("png") => load_png(path)?,
+        _ => return Err("unsupported image format".into()),
+    };
+    if sprite.width == 0 || sprite.height == 0 {
+        return Err("image is empty".into());
+    }
+    if sprite.width > MAX_WIDTH || sprite.height > MAX_HEIGHT {
+        return Err(format!(
+            "{}x{} doesn't fit the {}x{} screen",
+            sprite.width, sprite.height, MAX_WIDTH, MAX_HEIGHT
+        ));
+    }
+    Ok(sprite)
+}
+
+fn generate_sprites(out: &Path) {
+    let mut generated = File::create(out.join("sprites.rs")).unwrap();
+    for (name, file_name) in SPRITES {
+        let path = Path::new(ASSET_DIR).join(file_name);
+        println!("cargo:rerun-if-changed={}", path.display());
+        let sprite = load_sprite(&path)
+            .unwrap_or_else(|e| panic!("Malformed asset {}: {}", path.display(), e));
+        let bytes = sprite
+            .pack()
+            .iter()
+            .map(|b| format!("{:#04x}", b))
+            .collect::<Vec<_>>()
+            .join(", ");
+        writeln!(
+            generated,
+            "// {file_name}\n\
+             pub const {name}_WIDTH: u8 = {width};\n\
+             pub const {name}_HEIGHT: u8 = {height};\n\
+             pub const {name}: ImageRaw<BinaryColor> =\n    \
+             ImageRaw::new(&[{bytes}], {name}_WIDTH as u32);",
+            width = sprite.width,
+            height = sprite.height,
+        )
+        .unwrap();
+    }
+}
 
 fn main() {
     // Put `memory.x` in our output directory and ensure it's
@@ -18,4 +156,6 @@ fn main() {
     // here, we ensure the build script is only re-run when
     // `memory.x` is changed.
     println!("cargo:rerun-if-changed=memory.x");
+
+    generate_sprites(out);
 }
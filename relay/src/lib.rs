@@ -0,0 +1,7 @@
+// Relay for online versus: pairs two clients and passes their inputs along,
+// the game itself only runs on the clients. The protocol is shared with both
+// clients, WebSockets are tungstenite's on native and the browser's on WASM.
+
+pub mod protocol;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod relay;
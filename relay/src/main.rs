@@ -0,0 +1,13 @@
+use std::net::TcpListener;
+
+const DEFAULT_ADDR: &str = "127.0.0.1:9001";
+
+// `poodg-relay [address]`, listens on 127.0.0.1:9001 by default
+fn main() -> std::io::Result<()> {
+    let addr = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| DEFAULT_ADDR.to_string());
+    let listener = TcpListener::bind(&addr)?;
+    println!("Relay listening on ws://{}", listener.local_addr()?);
+    poodg_relay::relay::serve(listener)
+}
@@ -0,0 +1,128 @@
+use std::fmt;
+
+pub const PROTOCOL_VERSION: u8 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Message {
+    // Client -> relay right after connecting. Only clients of the same
+    // `build` get paired, tick rates differ between platforms.
+    Hello { version: u8, build: u8 },
+    // Relay -> both clients once paired, `player` is 0 or 1
+    Start { player: u8, seed: u64 },
+    // Control for `tick`, client -> relay -> the other client
+    Input { tick: u32, control: u8 },
+    // Relay -> client, the other client went away
+    PeerLeft,
+    // Relay -> client, wrong version or no matching build waiting
+    Rejected,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProtocolError {
+    Empty,
+    UnknownKind(u8),
+    // Payload of the wrong size for its kind
+    Length(u8),
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProtocolError::Empty => write!(f, "empty message"),
+            ProtocolError::UnknownKind(kind) => write!(f, "unknown message kind {}", kind),
+            ProtocolError::Length(kind) => write!(f, "bad length for message kind {}", kind),
+        }
+    }
+}
+
+const HELLO: u8 = 0;
+const START: u8 = 1;
+const INPUT: u8 = 2;
+const PEER_LEFT: u8 = 3;
+const REJECTED: u8 = 4;
+
+impl Message {
+    // Kind byte, then the fields little endian
+    pub fn encode(&self) -> Vec<u8> {
+        match *self {
+            Message::Hello { version, build } => vec![HELLO, version, build],
+            Message::Start { player, seed } => {
+                let mut bytes = vec![START, player];
+                bytes.extend_from_slice(&seed.to_le_bytes());
+                bytes
+            }
+            Message::Input { tick, control } => {
+                let mut bytes = vec![INPUT];
+                bytes.extend_from_slice(&tick.to_le_bytes());
+                bytes.push(control);
+                bytes
+            }
+            Message::PeerLeft => vec![PEER_LEFT],
+            Message::Rejected => vec![REJECTED],
+        }
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Message, ProtocolError> {
+        let (&kind, body) = bytes.split_first().ok_or(ProtocolError::Empty)?;
+        let expected_len = match kind {
+            HELLO => 2,
+            START => 9,
+            INPUT => 5,
+            PEER_LEFT | REJECTED => 0,
+            _ => return Err(ProtocolError::UnknownKind(kind)),
+        };
+        if body.len() != expected_len {
+            return Err(ProtocolError::Length(kind));
+        }
+        Ok(match kind {
+            HELLO => Message::Hello {
+                version: body[0],
+                build: body[1],
+            },
+            START => Message::Start {
+                player: body[0],
+                seed: u64::from_le_bytes(body[1..9].try_into().unwrap()),
+            },
+            INPUT => Message::Input {
+                tick: u32::from_le_bytes(body[0..4].try_into().unwrap()),
+                control: body[4],
+            },
+            PEER_LEFT => Message::PeerLeft,
+            _ => Message::Rejected,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let messages = [
+            Message::Hello {
+                version: PROTOCOL_VERSION,
+                build: 1,
+            },
+            Message::Start {
+                player: 1,
+                seed: u64::MAX - 7,
+            },
+            Message::Input {
+                tick: 70_000,
+                control: 2,
+            },
+            Message::PeerLeft,
+            Message::Rejected,
+        ];
+        for message in messages {
+            assert_eq!(Message::decode(&message.encode()), Ok(message));
+        }
+        assert_eq!(Message::decode(&[]), Err(ProtocolError::Empty));
+        assert_eq!(Message::decode(&[9]), Err(ProtocolError::UnknownKind(9)));
+        assert_eq!(
+            Message::decode(&[INPUT, 1, 0]),
+            Err(ProtocolError::Length(INPUT))
+        );
+    }
+}
@@ -0,0 +1,271 @@
+use std::io;
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tungstenite::{HandshakeError, Message as Frame, WebSocket};
+
+use crate::protocol::{Message, PROTOCOL_VERSION};
+
+// How long a client's thread waits on its socket before passing on what its
+// peer sent, bounds the latency the relay adds to inputs
+const POLL_INTERVAL: Duration = Duration::from_millis(2);
+
+// What the other clients' threads send to this one
+type Outbox = Sender<Message>;
+// Where a client's inputs go, empty until it's paired and again once the peer leaves
+type PeerSlot = Arc<Mutex<Option<Outbox>>>;
+
+// A client that said hello and waits for a second one of the same build
+struct Waiting {
+    id: u64,
+    build: u8,
+    outbox: Outbox,
+    peer: PeerSlot,
+}
+
+type Lobby = Arc<Mutex<Option<Waiting>>>;
+
+// One thread per client. Clients are paired in the order they connect.
+pub fn serve(listener: TcpListener) -> io::Result<()> {
+    let lobby: Lobby = Arc::new(Mutex::new(None));
+    for (id, stream) in listener.incoming().enumerate() {
+        // A failed accept only concerns that one client
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                println!("Couldn't accept client, {}", err);
+                continue;
+            }
+        };
+        let lobby = lobby.clone();
+        thread::spawn(move || {
+            let addr = stream.peer_addr().ok();
+            if let Err(err) = handle_client(id as u64, stream, &lobby) {
+                println!("Client {:?} dropped, {}", addr, err);
+            }
+        });
+    }
+    Ok(())
+}
+
+// Everything but IO errors is the client not speaking WebSocket properly
+fn io_error(err: tungstenite::Error) -> io::Error {
+    match err {
+        tungstenite::Error::Io(err) => err,
+        err => io::Error::new(io::ErrorKind::InvalidData, err),
+    }
+}
+
+fn send(socket: &mut WebSocket<TcpStream>, message: &Message) -> io::Result<()> {
+    socket
+        .send(Frame::Binary(message.encode()))
+        .map_err(io_error)
+}
+
+fn handle_client(id: u64, stream: TcpStream, lobby: &Lobby) -> io::Result<()> {
+    stream.set_nodelay(true)?;
+    let mut socket = tungstenite::accept(stream).map_err(|err| match err {
+        HandshakeError::Failure(err) => io_error(err),
+        // Only non-blocking sockets get interrupted
+        HandshakeError::Interrupted(_) => io::ErrorKind::WouldBlock.into(),
+    })?;
+    let (outbox, inbox) = mpsc::channel();
+    let peer: PeerSlot = Arc::new(Mutex::new(None));
+
+    let Some(build) = read_hello(&mut socket)? else {
+        send(&mut socket, &Message::Rejected)?;
+        return socket.close(None).map_err(io_error);
+    };
+    if !join_lobby(id, build, &mut socket, &outbox, &peer, lobby)? {
+        send(&mut socket, &Message::Rejected)?;
+        return socket.close(None).map_err(io_error);
+    }
+
+    let result = forward_inputs(&mut socket, &inbox, &peer);
+
+    // Still waiting, nobody to tell
+    let mut waiting = lobby.lock().unwrap();
+    if waiting.as_ref().is_some_and(|waiting| waiting.id == id) {
+        *waiting = None;
+    }
+    drop(waiting);
+    if let Some(peer_outbox) = peer.lock().unwrap().take() {
+        let _ = peer_outbox.send(Message::PeerLeft);
+    }
+    result
+}
+
+// The client's build, `None` for a version this relay doesn't speak
+fn read_hello(socket: &mut WebSocket<TcpStream>) -> io::Result<Option<u8>> {
+    loop {
+        match socket.read().map_err(io_error)? {
+            Frame::Binary(payload) => {
+                return match Message::decode(&payload) {
+                    Ok(Message::Hello { version, build }) if version == PROTOCOL_VERSION => {
+                        Ok(Some(build))
+                    }
+                    _ => Ok(None),
+                };
+            }
+            Frame::Close(_) => {
+                // Sends the Close frame tungstenite queued in reply
+                socket.flush().map_err(io_error)?;
+                return Err(io::ErrorKind::ConnectionAborted.into());
+            }
+            // Pings are answered by tungstenite
+            _ => {}
+        }
+    }
+}
+
+// Pairs with the waiting client or becomes the waiting one.
+// False when the waiting client is of another build.
+fn join_lobby(
+    id: u64,
+    build: u8,
+    socket: &mut WebSocket<TcpStream>,
+    outbox: &Outbox,
+    peer: &PeerSlot,
+    lobby: &Lobby,
+) -> io::Result<bool> {
+    let mut waiting = lobby.lock().unwrap();
+    match waiting.take() {
+        Some(first) if first.build == build => {
+            let seed = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|time| time.as_nanos() as u64)
+                .unwrap_or_default();
+            *first.peer.lock().unwrap() = Some(outbox.clone());
+            *peer.lock().unwrap() = Some(first.outbox.clone());
+            // The first one might be gone already, it tells us with `PeerLeft`
+            let _ = first.outbox.send(Message::Start { player: 0, seed });
+            send(socket, &Message::Start { player: 1, seed })?;
+            Ok(true)
+        }
+        Some(first) => {
+            *waiting = Some(first);
+            Ok(false)
+        }
+        None => {
+            *waiting = Some(Waiting {
+                id,
+                build,
+                outbox: outbox.clone(),
+                peer: peer.clone(),
+            });
+            Ok(true)
+        }
+    }
+}
+
+// Passes inputs to the peer as they are and the peer's messages to the
+// client, until either side goes away
+fn forward_inputs(
+    socket: &mut WebSocket<TcpStream>,
+    inbox: &Receiver<Message>,
+    peer: &PeerSlot,
+) -> io::Result<()> {
+    socket.get_ref().set_read_timeout(Some(POLL_INTERVAL))?;
+    loop {
+        match socket.read().map_err(io_error) {
+            Ok(Frame::Binary(payload)) => {
+                let Ok(input @ Message::Input { .. }) = Message::decode(&payload) else {
+                    continue;
+                };
+                if let Some(peer_outbox) = peer.lock().unwrap().as_ref() {
+                    // A dead peer shows up on its own thread
+                    let _ = peer_outbox.send(input);
+                }
+            }
+            Ok(Frame::Close(_)) => {
+                // Sends the Close frame tungstenite queued in reply
+                return socket.flush().map_err(io_error);
+            }
+            Ok(_) => {}
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) => {}
+            Err(err) => return Err(err),
+        }
+        while let Ok(message) = inbox.try_recv() {
+            send(socket, &message)?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn start_relay() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || serve(listener));
+        addr
+    }
+
+    fn client(addr: &str, build: u8) -> WebSocket<TcpStream> {
+        let stream = TcpStream::connect(addr).unwrap();
+        let (mut socket, _) = tungstenite::client(format!("ws://{}/", addr), stream).unwrap();
+        let hello = Message::Hello {
+            version: PROTOCOL_VERSION,
+            build,
+        };
+        send(&mut socket, &hello).unwrap();
+        socket
+    }
+
+    fn receive(socket: &mut WebSocket<TcpStream>) -> Message {
+        match socket.read().unwrap() {
+            Frame::Binary(payload) => Message::decode(&payload).unwrap(),
+            frame => panic!("unexpected frame {:?}", frame),
+        }
+    }
+
+    #[test]
+    fn test_pairs_clients_and_forwards_inputs() {
+        let addr = start_relay();
+        let mut first = client(&addr, 0);
+        // The first client has to be waiting before the second says hello
+        thread::sleep(Duration::from_millis(50));
+        let mut second = client(&addr, 0);
+
+        let (
+            Message::Start { player: 0, seed },
+            Message::Start {
+                player: 1,
+                seed: seed_2,
+            },
+        ) = (receive(&mut first), receive(&mut second))
+        else {
+            panic!("clients weren't paired");
+        };
+        assert_eq!(seed, seed_2);
+
+        let input = Message::Input {
+            tick: 3,
+            control: 2,
+        };
+        send(&mut first, &input).unwrap();
+        assert_eq!(receive(&mut second), input);
+
+        // The relay answers the Close
+        first.close(None).unwrap();
+        assert!(matches!(first.read(), Ok(Frame::Close(_))));
+        assert_eq!(receive(&mut second), Message::PeerLeft);
+    }
+
+    #[test]
+    fn test_rejects_other_builds() {
+        let addr = start_relay();
+        let _first = client(&addr, 0);
+        thread::sleep(Duration::from_millis(50));
+        let mut second = client(&addr, 1);
+        assert_eq!(receive(&mut second), Message::Rejected);
+    }
+}
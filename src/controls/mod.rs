@@ -10,7 +10,35 @@ pub enum ControlEnum {
     Right = 1,
     Hook,
     None,
+    // Debug toggles, never reach Pudge
+    Debug,
+    Hitboxes,
+    // Pushes or pops the pause scene
+    Pause,
 }
+// Player one plays from the bottom, player two from the top in versus
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Player {
+    One,
+    Two,
+}
+
+impl Player {
+    pub fn index(&self) -> usize {
+        match self {
+            Player::One => 0,
+            Player::Two => 1,
+        }
+    }
+
+    pub fn other(&self) -> Player {
+        match self {
+            Player::One => Player::Two,
+            Player::Two => Player::One,
+        }
+    }
+}
+
 impl ControlEnum {
     pub fn is_none(&self) -> bool {
         *self == ControlEnum::None
@@ -28,6 +56,19 @@ impl Format for ControlEnum {
             ControlEnum::Right => defmt::write!(f, "Right"),
             ControlEnum::Hook => defmt::write!(f, "Hook"),
             ControlEnum::None => defmt::write!(f, "None"),
+            ControlEnum::Debug => defmt::write!(f, "Debug"),
+            ControlEnum::Hitboxes => defmt::write!(f, "Hitboxes"),
+            ControlEnum::Pause => defmt::write!(f, "Pause"),
+        }
+    }
+}
+
+#[cfg(target_os = "none")]
+impl Format for Player {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            Player::One => defmt::write!(f, "P1"),
+            Player::Two => defmt::write!(f, "P2"),
         }
     }
 }
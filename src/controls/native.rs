@@ -4,7 +4,7 @@ use embedded_graphics_simulator::{
     SimulatorEvent,
 };
 
-use super::ControlEnum;
+use super::{ControlEnum, Player};
 
 pub struct WheelControl {
     pub wheel_delta: Point,
@@ -33,20 +33,27 @@ impl From<MouseButton> for ControlEnum {
     }
 }
 
-impl From<Keycode> for ControlEnum {
-    fn from(value: Keycode) -> Self {
-        match value {
-            Keycode::Left | Keycode::A => ControlEnum::Left,
-            Keycode::Right | Keycode::D => ControlEnum::Right,
-            Keycode::Space | Keycode::W => ControlEnum::Hook,
-            _ => ControlEnum::None,
-        }
+// Keyboard halves: A/D/W and Space for player one, the arrows and J/L/I for player two.
+// P or Escape pauses.
+fn key_control(keycode: Keycode) -> (Player, ControlEnum) {
+    match keycode {
+        Keycode::A => (Player::One, ControlEnum::Left),
+        Keycode::D => (Player::One, ControlEnum::Right),
+        Keycode::W | Keycode::Space => (Player::One, ControlEnum::Hook),
+        Keycode::Left | Keycode::J => (Player::Two, ControlEnum::Left),
+        Keycode::Right | Keycode::L => (Player::Two, ControlEnum::Right),
+        Keycode::Up | Keycode::I => (Player::Two, ControlEnum::Hook),
+        Keycode::F3 => (Player::One, ControlEnum::Debug),
+        Keycode::F4 => (Player::One, ControlEnum::Hitboxes),
+        Keycode::P | Keycode::Escape => (Player::One, ControlEnum::Pause),
+        _ => (Player::One, ControlEnum::None),
     }
 }
 
+// Last control of the frame for each player, indexed by `Player::index`
 pub fn window_controls(
     win: &mut embedded_graphics_simulator::Window,
-    controls: &mut Option<ControlEnum>,
+    controls: &mut [Option<ControlEnum>; 2],
 ) -> Result<(), ()> {
     Ok(for event in win.events() {
         // Will be easier to refactor after controller interface implementation
@@ -61,7 +68,7 @@ pub fn window_controls(
                     direction,
                 });
                 if wheel_control.is_some() {
-                    *controls = Some(wheel_control);
+                    controls[Player::One.index()] = Some(wheel_control);
                 }
                 Ok(())
             }
@@ -70,16 +77,16 @@ pub fn window_controls(
                 keymod,
                 repeat,
             } => {
-                let kbd_control = ControlEnum::from(keycode);
+                let (player, kbd_control) = key_control(keycode);
                 if kbd_control.is_some() {
-                    *controls = Some(kbd_control);
+                    controls[player.index()] = Some(kbd_control);
                 }
                 Ok(())
             }
             SimulatorEvent::MouseButtonDown { mouse_btn, point } => {
                 let mouse_control = ControlEnum::from(mouse_btn);
                 if mouse_control.is_some() {
-                    *controls = Some(mouse_control);
+                    controls[Player::One.index()] = Some(mouse_control);
                 }
                 Ok(())
             }
@@ -1,13 +1,13 @@
 use core::cell::RefCell;
 
 use critical_section::Mutex;
-use embedded_alloc::LlffHeap as Heap;
 use embedded_graphics::geometry::Point;
 use embedded_graphics::image::Image;
 use embedded_hal::digital::InputPin;
 use embedded_hal::digital::OutputPin;
 use fugit::HertzU32;
 use fugit::RateExtU32;
+use heapless::Deque;
 use pac::interrupt;
 
 use defmt::*;
@@ -22,13 +22,11 @@ use rp2040_hal::Timer;
 use rp2040_hal::{clocks::init_clocks_and_plls, pac, Watchdog, I2C};
 extern crate cortex_m_rt;
 extern crate ssd1309;
-#[global_allocator]
-static HEAP: Heap = Heap::empty();
 use ssd1309::displayrotation::DisplayRotation;
 use ssd1309::mode::GraphicsMode;
 use ssd1309::Builder;
 
-use crate::controls::ControlEnum;
+use crate::controls::{ControlEnum, Player};
 use crate::game::Game;
 use crate::graphics::display::clear_display;
 use crate::graphics::display::DisplayEnum;
@@ -52,23 +50,126 @@ pub enum Direction {
     Clockwise,
     CounterClock,
 }
-type Enc1Pin = gpio::Pin<gpio::bank0::Gpio18, gpio::FunctionSioInput, gpio::PullUp>;
-type Enc2Pin = gpio::Pin<gpio::bank0::Gpio19, gpio::FunctionSioInput, gpio::PullUp>;
+// Both encoders' pins are type erased so they fit in one array
+type EncPin = gpio::Pin<gpio::DynPinId, gpio::FunctionSioInput, gpio::PullUp>;
 type LEDPin = gpio::Pin<gpio::bank0::Gpio25, gpio::FunctionSioOutput, gpio::PullUp>;
-type EncBtnPin = gpio::Pin<gpio::bank0::Gpio20, gpio::FunctionSioInput, gpio::PullUp>;
-type EncoderPins = (Enc1Pin, Enc2Pin, EncBtnPin);
 
-static ENCODER: Mutex<RefCell<Option<EncoderPins>>> = Mutex::new(RefCell::new(None));
-static ACTION: Mutex<RefCell<[Option<ControlEnum>; 8]>> = Mutex::new(RefCell::new([None; 8]));
+struct Encoder {
+    player: Player,
+    enc_a: EncPin,
+    enc_b: EncPin,
+    enc_btn: EncPin,
+    rotary: Rotary,
+    direction: Option<Direction>,
+}
+
+// Player one's encoder is on GPIO 18/19 with the button on 20,
+// player two's on GPIO 10/11 with the button on 12
+static ENCODERS: Mutex<RefCell<Option<[Encoder; 2]>>> = Mutex::new(RefCell::new(None));
+// Repeats of the same input share a slot with a count, so fast encoder
+// spins between two frames don't fill the queue
+const ACTION_RUNS: usize = 8;
+type ActionQueue = Deque<(Player, ControlEnum, u8), ACTION_RUNS>;
+static ACTION: Mutex<RefCell<ActionQueue>> = Mutex::new(RefCell::new(Deque::new()));
+
+fn queue_action(player: Player, action: ControlEnum) {
+    critical_section::with(|cs| {
+        let mut actions = ACTION.borrow_ref_mut(cs);
+        if let Some((last_player, last, count)) = actions.back_mut() {
+            if *last_player == player && *last == action && *count < u8::MAX {
+                *count += 1;
+                return;
+            }
+        }
+        if actions.push_back((player, action, 1)).is_err() {
+            warn!("Input queue full, dropped {} for {}", action, player);
+        }
+    });
+}
+
+impl Encoder {
+    fn new(player: Player, enc_a: EncPin, enc_b: EncPin, enc_btn: EncPin) -> Self {
+        enc_a.set_interrupt_enabled(Interrupt::EdgeHigh, true);
+        enc_b.set_interrupt_enabled(Interrupt::EdgeHigh, true);
+        enc_a.set_interrupt_enabled(Interrupt::EdgeLow, true);
+        enc_b.set_interrupt_enabled(Interrupt::EdgeLow, true);
+        enc_btn.set_interrupt_enabled(Interrupt::EdgeLow, true);
+        Encoder {
+            player,
+            enc_a,
+            enc_b,
+            enc_btn,
+            rotary: Rotary::Rotary0,
+            direction: None,
+        }
+    }
+
+    // Both encoders share the bank interrupt, so this runs for the other one's edges too
+    fn poll(&mut self) {
+        self.enc_a.clear_interrupt(Interrupt::EdgeLow);
+        self.enc_a.clear_interrupt(Interrupt::EdgeHigh);
+        self.enc_b.clear_interrupt(Interrupt::EdgeLow);
+        self.enc_b.clear_interrupt(Interrupt::EdgeHigh);
+        let pressed = self.enc_btn.interrupt_status(Interrupt::EdgeLow);
+        self.enc_btn.clear_interrupt(Interrupt::EdgeLow);
+        let mut new_action: Option<ControlEnum> = None;
+
+        if let (Ok(enc1_hi), Ok(enc2_hi)) = (self.enc_a.is_high(), self.enc_b.is_high()) {
+            match (&self.rotary, &self.direction, enc1_hi, enc2_hi) {
+                // Rotate clockwise
+                (Rotary::Rotary0, _, false, true) => {
+                    self.direction = Some(Direction::Clockwise);
+                    self.rotary = Rotary::Rotary1;
+                }
+                (Rotary::Rotary3, Some(Direction::Clockwise), true, true) => {
+                    new_action = Some(ControlEnum::Right);
+                    self.rotary = Rotary::Rotary0;
+                    self.direction = None;
+                }
+
+                // Rotate counter-clock
+                (Rotary::Rotary0, _, true, false) => {
+                    self.direction = Some(Direction::CounterClock);
+                    self.rotary = Rotary::Rotary3;
+                }
+                (Rotary::Rotary1, Some(Direction::CounterClock), true, true) => {
+                    new_action = Some(ControlEnum::Left);
+                    self.rotary = Rotary::Rotary0;
+                    self.direction = None;
+                }
+
+                // misc
+                (_, _, true, true) => {
+                    self.rotary = Rotary::Rotary0;
+                    self.direction = None;
+                }
+                (_, _, false, true) => {
+                    self.rotary = Rotary::Rotary1;
+                }
+                (_, _, false, false) => {
+                    self.rotary = Rotary::Rotary2;
+                }
+                (_, _, true, false) => {
+                    self.rotary = Rotary::Rotary3;
+                }
+            }
+            if let Some(action) = new_action {
+                queue_action(self.player, action);
+            }
+        }
+
+        if !pressed {
+            return;
+        }
+        if let Ok(false) = self.enc_btn.is_high() {
+            debug!("Hooking!");
+            queue_action(self.player, ControlEnum::Hook);
+        }
+    }
+}
 
 pub fn embed_main() -> ! {
     info!("Starting main");
-    {
-        use core::mem::MaybeUninit;
-        const HEAP_SIZE: usize = 1024;
-        static mut HEAP_MEM: [MaybeUninit<u8>; HEAP_SIZE] = [MaybeUninit::uninit(); HEAP_SIZE];
-        unsafe { HEAP.init(HEAP_MEM.as_ptr() as usize, HEAP_SIZE) }
-    }
     let mut pac = pac::Peripherals::take().unwrap();
     let core = pac::CorePeripherals::take().unwrap();
     let mut rosc = RingOscillator::new(pac.ROSC).initialize();
@@ -121,18 +222,24 @@ pub fn embed_main() -> ! {
     let mut game = Game::default();
     let timer = Timer::new(pac.TIMER, &mut pac.RESETS, &clocks);
 
-    let enc_1: Enc1Pin = pins.gpio18.reconfigure();
-    let enc_2: Enc2Pin = pins.gpio19.reconfigure();
-    let enc_btn: EncBtnPin = pins.gpio20.reconfigure();
+    let encoders = [
+        Encoder::new(
+            Player::One,
+            pins.gpio18.reconfigure().into_dyn_pin(),
+            pins.gpio19.reconfigure().into_dyn_pin(),
+            pins.gpio20.reconfigure().into_dyn_pin(),
+        ),
+        Encoder::new(
+            Player::Two,
+            pins.gpio10.reconfigure().into_dyn_pin(),
+            pins.gpio11.reconfigure().into_dyn_pin(),
+            pins.gpio12.reconfigure().into_dyn_pin(),
+        ),
+    ];
     let mut led: LEDPin = pins.gpio25.reconfigure();
     let _ = led.set_high();
-    enc_1.set_interrupt_enabled(gpio::Interrupt::EdgeHigh, true);
-    enc_2.set_interrupt_enabled(gpio::Interrupt::EdgeHigh, true);
-    enc_1.set_interrupt_enabled(gpio::Interrupt::EdgeLow, true);
-    enc_2.set_interrupt_enabled(gpio::Interrupt::EdgeLow, true);
-    enc_btn.set_interrupt_enabled(Interrupt::EdgeLow, true);
     critical_section::with(|cs| {
-        ENCODER.borrow(cs).replace(Some((enc_1, enc_2, enc_btn)));
+        ENCODERS.borrow(cs).replace(Some(encoders));
     });
     unsafe {
         pac::NVIC::unmask(pac::Interrupt::IO_IRQ_BANK0);
@@ -146,117 +253,47 @@ pub fn embed_main() -> ! {
 
 pub fn main_loop(display: &mut DisplayEnum, game: &mut Game, timer: &Timer) {
     let tick = timer.get_counter();
-    let mut action_queue: [Option<ControlEnum>; 8] = [None; 8];
-    critical_section::with(|cs| {
-        action_queue = (*ACTION.borrow(cs)).take();
-    });
+    let action_queue: ActionQueue = critical_section::with(|cs| ACTION.borrow(cs).take());
 
-    for action in action_queue {
-        if action.is_none() {
-            break;
+    for (player, action, count) in action_queue {
+        for _ in 0..count {
+            game.control(player, action);
         }
-        game.control(action.unwrap());
     }
 
-    game.process(tick);
+    if let Err(err) = game.process(tick) {
+        warn!("Skipped tick, {}", err);
+    }
+    // Nothing reads the events here, dropped so the queue only overflows for real
+    let _ = game.drain_events();
 
     clear_display(display);
     game.draw(display);
     if let DisplayEnum::Oled(ref mut disp) = display {
-        disp.flush().unwrap()
+        let flush_start = timer.get_counter();
+        // A failed transfer only loses this frame
+        if let Err(err) = disp.flush() {
+            warn!("Flush failed, {}", Debug2Format(&err));
+        } else if let Some(flush) = timer.get_counter().checked_duration_since(flush_start) {
+            game.report_flush(flush);
+        }
     }
 }
 
 #[interrupt]
 fn IO_IRQ_BANK0() {
-    static mut ENCODER_PINS: Option<EncoderPins> = None;
-    static mut ROTARY: Rotary = Rotary::Rotary0;
-    static mut DIRECTION: Option<Direction> = None;
+    static mut ENCODER_PINS: Option<[Encoder; 2]> = None;
 
     debug!("Interrupt!");
     if ENCODER_PINS.is_none() {
         critical_section::with(|cs| {
-            *ENCODER_PINS = ENCODER.borrow(cs).take();
+            *ENCODER_PINS = ENCODERS.borrow(cs).take();
         });
     }
 
-    if let Some(gpios) = ENCODER_PINS {
-        let (enc_a, enc_b, enc_btn) = gpios;
-        enc_a.clear_interrupt(gpio::Interrupt::EdgeLow);
-        enc_a.clear_interrupt(gpio::Interrupt::EdgeHigh);
-        enc_b.clear_interrupt(gpio::Interrupt::EdgeLow);
-        enc_b.clear_interrupt(gpio::Interrupt::EdgeHigh);
-        enc_btn.clear_interrupt(Interrupt::EdgeLow);
-        let mut new_action: Option<ControlEnum> = None;
-
-        if let (Ok(enc1_hi), Ok(enc2_hi)) = (enc_a.is_high(), enc_b.is_high()) {
-            match (&ROTARY, &DIRECTION, enc1_hi, enc2_hi) {
-                // Rotate clockwise
-                (Rotary::Rotary0, _, false, true) => {
-                    *DIRECTION = Some(Direction::Clockwise);
-                    *ROTARY = Rotary::Rotary1;
-                }
-                (Rotary::Rotary3, Some(Direction::Clockwise), true, true) => {
-                    new_action = Some(ControlEnum::Right);
-                    *ROTARY = Rotary::Rotary0;
-                    *DIRECTION = None;
-                }
-
-                // Rotate counter-clock
-                (Rotary::Rotary0, _, true, false) => {
-                    *DIRECTION = Some(Direction::CounterClock);
-                    *ROTARY = Rotary::Rotary3;
-                }
-                (Rotary::Rotary1, Some(Direction::CounterClock), true, true) => {
-                    new_action = Some(ControlEnum::Left);
-                    *ROTARY = Rotary::Rotary0;
-                    *DIRECTION = None;
-                }
-
-                // misc
-                (_, _, true, true) => {
-                    *ROTARY = Rotary::Rotary0;
-                    *DIRECTION = None;
-                }
-                (_, _, false, true) => {
-                    *ROTARY = Rotary::Rotary1;
-                }
-                (_, _, false, false) => {
-                    *ROTARY = Rotary::Rotary2;
-                }
-                (_, _, true, false) => {
-                    *ROTARY = Rotary::Rotary3;
-                }
-            }
-            critical_section::with(|cs| {
-                if new_action.is_some() {
-                    let mut actions = ACTION.borrow(cs).take();
-                    for action in actions.iter_mut() {
-                        if action.is_none() {
-                            *action = Some(new_action.unwrap());
-                            break;
-                        }
-                    }
-                    ACTION.replace(cs, actions);
-                }
-            });
-        }
-
-        if let Ok(enc_btn_hi) = enc_btn.is_high() {
-            if enc_btn_hi {
-                return;
-            }
-            debug!("Hooking!");
-            critical_section::with(|cs| {
-                let mut actions = ACTION.borrow(cs).take();
-                for action in actions.iter_mut() {
-                    if action.is_none() {
-                        *action = Some(ControlEnum::Hook);
-                        break;
-                    }
-                }
-                ACTION.replace(cs, actions);
-            });
+    if let Some(encoders) = ENCODER_PINS {
+        for encoder in encoders.iter_mut() {
+            encoder.poll();
         }
     }
 }
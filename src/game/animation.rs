@@ -0,0 +1,87 @@
+use embedded_graphics::{image::ImageRaw, pixelcolor::BinaryColor};
+
+use crate::graphics::resources::Sprite;
+
+use super::TickCount;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Animation {
+    sprite: Sprite,
+    frame: usize,
+    elapsed: TickCount,
+    ticks_per_frame: TickCount,
+}
+
+impl Animation {
+    pub fn new(sprite: Sprite, ticks_per_frame: TickCount) -> Self {
+        Animation {
+            sprite,
+            frame: 0,
+            elapsed: 0,
+            ticks_per_frame,
+        }
+    }
+
+    pub fn tick(&mut self) {
+        self.elapsed += 1;
+        if self.elapsed >= self.ticks_per_frame {
+            self.elapsed = 0;
+            self.frame = (self.frame + 1) % self.sprite.get_frames().len();
+        }
+    }
+
+    // Switches to another sheet, restarting only if it's actually different
+    pub fn play(&mut self, sprite: Sprite) {
+        if self.sprite == sprite {
+            return;
+        }
+        self.sprite = sprite;
+        self.frame = 0;
+        self.elapsed = 0;
+    }
+
+    pub fn image(&self) -> ImageRaw<'static, BinaryColor> {
+        self.sprite.get_frame(self.frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frames_step_and_loop() {
+        let mut animation = Animation::new(Sprite::PudgeReeling, 3);
+        let frames = Sprite::PudgeReeling.get_frames().len();
+        assert!(frames > 1);
+
+        for _ in 0..2 {
+            animation.tick();
+        }
+        assert_eq!(animation.frame, 0);
+        animation.tick();
+        assert_eq!(animation.frame, 1);
+        assert_eq!(animation.elapsed, 0);
+
+        for _ in 0..3 * (frames - 1) {
+            animation.tick();
+        }
+        assert_eq!(animation.frame, 0);
+    }
+
+    #[test]
+    fn test_play_restarts_only_on_another_sheet() {
+        let mut animation = Animation::new(Sprite::PudgeReeling, 3);
+        for _ in 0..4 {
+            animation.tick();
+        }
+        assert_eq!((animation.frame, animation.elapsed), (1, 1));
+
+        animation.play(Sprite::PudgeReeling);
+        assert_eq!((animation.frame, animation.elapsed), (1, 1));
+
+        animation.play(Sprite::Pudge);
+        assert_eq!(animation.sprite, Sprite::Pudge);
+        assert_eq!((animation.frame, animation.elapsed), (0, 0));
+    }
+}
@@ -0,0 +1,101 @@
+use super::{Game, Hookable, Scene};
+use crate::controls::{ControlEnum, Player};
+
+// Plays from what's on screen like a player would: walks under the creep of
+// its side it can line up with soonest and throws once the hook would meet it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bot {
+    player: Player,
+}
+
+impl Bot {
+    pub fn new(player: Player) -> Self {
+        Bot { player }
+    }
+
+    pub fn player(&self) -> Player {
+        self.player
+    }
+
+    // The control for the next tick, `ControlEnum::None` while there's
+    // nothing to go for
+    pub fn control(&self, game: &Game) -> ControlEnum {
+        // On to the menu
+        if game.scene() == Scene::Title {
+            return ControlEnum::Hook;
+        }
+        let pudge = game.pudge(self.player);
+        if !pudge.is_walking() {
+            return ControlEnum::None;
+        }
+        let wanted = Hookable::Creep(pudge.side().creep());
+        let offset = game
+            .object_handler
+            .iter()
+            .filter(|object| object.game_type == wanted)
+            .filter_map(|object| pudge.aim_offset(object))
+            .min_by(|a, b| a.abs().total_cmp(&b.abs()));
+        match offset {
+            None => ControlEnum::None,
+            // As close as a step can get it
+            Some(offset) if offset.abs() <= pudge.walk_speed() / 2.0 => ControlEnum::Hook,
+            Some(offset) if offset < 0.0 => ControlEnum::Left,
+            Some(_) => ControlEnum::Right,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::testing::{new_game, tick_clock};
+    use crate::game::{GameEvent, ATTRACT_DELAY, TICK};
+
+    #[test]
+    fn test_bot_starts_a_run_and_scores() {
+        let mut game = new_game();
+        let bot = Bot::new(Player::One);
+        let mut scored = 0;
+        for tick in 0..3000 {
+            game.control(Player::One, bot.control(&game));
+            game.process(tick_clock(tick)).unwrap();
+            scored += game
+                .drain_events()
+                .filter(|event| matches!(event, GameEvent::Scored(_, points) if *points > 0))
+                .count();
+        }
+        assert!(scored >= 5, "bot only hooked {} creeps", scored);
+    }
+
+    #[test]
+    fn test_attract_mode_until_real_input() {
+        let mut game = new_game();
+        let idle_ticks = ATTRACT_DELAY.to_micros() / TICK.to_micros();
+        let mut tick = 0;
+        while !game.scene().is_playing() {
+            assert!(tick < idle_ticks + 1000, "demo never started");
+            game.process(tick_clock(tick)).unwrap();
+            tick += 1;
+        }
+        assert!(tick > idle_ticks);
+        assert!(game.in_attract_mode());
+
+        // Nobody's input, the demo keeps going
+        game.control(Player::One, ControlEnum::None);
+        game.process(tick_clock(tick)).unwrap();
+        assert!(game.scene().is_playing());
+
+        let _ = game.drain_events();
+        game.control(Player::One, ControlEnum::Left);
+        assert_eq!(game.scene(), Scene::Title);
+        assert!(!game.in_attract_mode());
+        assert_eq!(game.object_handler.len(), 0);
+
+        // Leaving the demo happens between ticks, the next tick must not lose it
+        game.process(tick_clock(tick + 1)).unwrap();
+        assert!(game.drain_events().any(|event| matches!(
+            event,
+            GameEvent::SceneChanged(Scene::Playing(_), Scene::Title)
+        )));
+    }
+}
@@ -1,6 +1,16 @@
+use embedded_graphics::{
+    image::{GetPixel, ImageRaw},
+    pixelcolor::BinaryColor,
+    prelude::{Point, Size},
+    primitives::Rectangle,
+};
+#[cfg(target_os = "none")]
+use libm::{ceilf, floorf, sqrtf};
+
+const AXES: [usize; 2] = [0, 1];
 use nalgebra::Vector2;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct CollisionRectangle {
     position: Vector2<f32>, // The top-left corner of the rectangle
     size: Vector2<f32>,     // The width and height of the rectangle
@@ -18,10 +28,189 @@ impl CollisionRectangle {
         let other_bottom = other.position.y + other.size.y;
 
         // Axis-Aligned Bounding Box (AABB) collision detection
-        !(self.position.x > other_right ||       // self is completely to the right of other
-          other.position.x > self_right ||       // other is completely to the right of self
-          self.position.y > other_bottom ||      // self is completely below other
-          other.position.y > self_bottom) // other is completely below self
+        !(self.position.x >= other_right ||       // self is completely to the right of other
+          other.position.x >= self_right ||       // other is completely to the right of self
+          self.position.y >= other_bottom ||      // self is completely below other
+          other.position.y >= self_bottom) // other is completely below self
+    }
+
+    // Narrow phase on top of the AABB check. Masks are 1-bit sprites drawn at the
+    // rectangle's floored position, a rectangle without one is solid everywhere.
+    pub fn intersects_masked(
+        &self,
+        self_mask: Option<&CollisionMask>,
+        other: &Self,
+        other_mask: Option<&CollisionMask>,
+    ) -> bool {
+        if !self.intersects(other) {
+            return false;
+        }
+        if self_mask.is_none() && other_mask.is_none() {
+            return true;
+        }
+
+        let overlap = Rectangle::from(&CollisionRectangle::new(
+            self.position.sup(&other.position),
+            (self.position + self.size).inf(&(other.position + other.size))
+                - self.position.sup(&other.position),
+        ));
+        for y in overlap.top_left.y..overlap.top_left.y + overlap.size.height as i32 {
+            for x in overlap.top_left.x..overlap.top_left.x + overlap.size.width as i32 {
+                let pixel = Point::new(x, y);
+                if self.is_solid(self_mask, pixel) && other.is_solid(other_mask, pixel) {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    // Shortest distance from `point` to the rectangle, 0.0 inside it
+    pub fn distance_to(&self, point: Vector2<f32>) -> f32 {
+        let far_corner = self.position + self.size;
+        let dx = (self.position.x - point.x)
+            .max(point.x - far_corner.x)
+            .max(0.0);
+        let dy = (self.position.y - point.y)
+            .max(point.y - far_corner.y)
+            .max(0.0);
+        #[cfg(not(target_os = "none"))]
+        return (dx * dx + dy * dy).sqrt();
+        #[cfg(target_os = "none")]
+        return sqrtf(dx * dx + dy * dy);
+    }
+
+    pub fn top(&self) -> f32 {
+        self.position.y
+    }
+
+    pub fn bottom(&self) -> f32 {
+        self.position.y + self.size.y
+    }
+
+    pub fn center(&self) -> Vector2<f32> {
+        self.position + self.size / 2.0
+    }
+
+    pub fn moved(&self, offset: Vector2<f32>) -> Self {
+        Self::new(self.position + offset, self.size)
+    }
+
+    // Swept AABB: `self` moves by `velocity` and `other` by `other_velocity` over
+    // one tick. Returns the time of impact as a fraction of the tick, 0.0 when
+    // they already overlap.
+    pub fn sweep(
+        &self,
+        velocity: Vector2<f32>,
+        other: &Self,
+        other_velocity: Vector2<f32>,
+    ) -> Option<f32> {
+        // Work in other's frame of reference
+        let relative = velocity - other_velocity;
+        let mut entry: f32 = 0.0;
+        let mut exit: f32 = 1.0;
+        for axis in AXES {
+            let self_min = self.position[axis];
+            let self_max = self_min + self.size[axis];
+            let other_min = other.position[axis];
+            let other_max = other_min + other.size[axis];
+            if relative[axis] == 0.0 {
+                if self_max <= other_min || self_min >= other_max {
+                    return None;
+                }
+                continue;
+            }
+            let t_first = (other_min - self_max) / relative[axis];
+            let t_second = (other_max - self_min) / relative[axis];
+            entry = entry.max(t_first.min(t_second));
+            exit = exit.min(t_first.max(t_second));
+        }
+        if entry >= exit {
+            return None;
+        }
+        Some(entry)
+    }
+
+    // Swept check refined with the masks. After the AABBs meet the pair is
+    // stepped at most a pixel at a time until lit pixels overlap.
+    pub fn sweep_masked(
+        &self,
+        self_mask: Option<&CollisionMask>,
+        velocity: Vector2<f32>,
+        other: &Self,
+        other_mask: Option<&CollisionMask>,
+        other_velocity: Vector2<f32>,
+    ) -> Option<f32> {
+        let entry = self.sweep(velocity, other, other_velocity)?;
+        if self_mask.is_none() && other_mask.is_none() {
+            return Some(entry);
+        }
+        let distance = (velocity - other_velocity).amax() * (1.0 - entry);
+        #[cfg(not(target_os = "none"))]
+        let steps = distance.ceil().max(1.0) as u32;
+        #[cfg(target_os = "none")]
+        let steps = ceilf(distance).max(1.0) as u32;
+        for step in 0..=steps {
+            let time = entry + (1.0 - entry) * step as f32 / steps as f32;
+            let self_at = self.moved(velocity * time);
+            let other_at = other.moved(other_velocity * time);
+            if self_at.intersects_masked(self_mask, &other_at, other_mask) {
+                return Some(time);
+            }
+        }
+        None
+    }
+
+    fn is_solid(&self, mask: Option<&CollisionMask>, pixel: Point) -> bool {
+        let bounds = Rectangle::from(self);
+        match mask {
+            Some(mask) => mask.is_solid(pixel - bounds.top_left),
+            None => {
+                // Pixel centre has to be inside the rectangle
+                let centre_x = pixel.x as f32 + 0.5;
+                let centre_y = pixel.y as f32 + 0.5;
+                centre_x >= self.position.x
+                    && centre_x < self.position.x + self.size.x
+                    && centre_y >= self.position.y
+                    && centre_y < self.position.y + self.size.y
+            }
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct CollisionMask {
+    image: ImageRaw<'static, BinaryColor>,
+}
+
+impl CollisionMask {
+    pub fn new(image: ImageRaw<'static, BinaryColor>) -> Self {
+        Self { image }
+    }
+
+    // Point relative to the sprite's top-left corner
+    fn is_solid(&self, point: Point) -> bool {
+        self.image.pixel(point) == Some(BinaryColor::On)
+    }
+}
+
+// Pixel area covered by the rectangle, for drawing it
+impl From<&CollisionRectangle> for Rectangle {
+    fn from(rect: &CollisionRectangle) -> Self {
+        #[cfg(not(target_os = "none"))]
+        let (x, y) = (rect.position.x.floor(), rect.position.y.floor());
+        #[cfg(target_os = "none")]
+        let (x, y) = (floorf(rect.position.x), floorf(rect.position.y));
+        let right = rect.position.x + rect.size.x;
+        let bottom = rect.position.y + rect.size.y;
+        #[cfg(not(target_os = "none"))]
+        let (right, bottom) = (right.ceil(), bottom.ceil());
+        #[cfg(target_os = "none")]
+        let (right, bottom) = (ceilf(right), ceilf(bottom));
+        Rectangle::new(
+            Point::new(x as i32, y as i32),
+            Size::new((right - x) as u32, (bottom - y) as u32),
+        )
     }
 }
 
@@ -73,4 +262,146 @@ mod tests {
         assert!(rect1.intersects(&rect2)); // Identical rectangles
         assert!(rect2.intersects(&rect1));
     }
+
+    // 4x4 ring, the centre and corners are transparent
+    const RING: ImageRaw<BinaryColor> =
+        ImageRaw::new(&[0b0110_0000, 0b1001_0000, 0b1001_0000, 0b0110_0000], 4);
+
+    #[test]
+    fn test_masked_transparent_corner_misses() {
+        let ring = CollisionRectangle::new(Vector2::new(0.0, 0.0), Vector2::new(4.0, 4.0));
+        let corner = CollisionRectangle::new(Vector2::new(-1.0, -1.0), Vector2::new(2.0, 2.0));
+        let mask = CollisionMask::new(RING);
+
+        assert!(ring.intersects(&corner)); // AABB still reports a hit
+        assert!(!ring.intersects_masked(Some(&mask), &corner, None));
+        assert!(!corner.intersects_masked(None, &ring, Some(&mask)));
+    }
+
+    #[test]
+    fn test_masked_transparent_centre_misses() {
+        let ring = CollisionRectangle::new(Vector2::new(10.0, 10.0), Vector2::new(4.0, 4.0));
+        let centre = CollisionRectangle::new(Vector2::new(11.0, 11.0), Vector2::new(2.0, 2.0));
+        let mask = CollisionMask::new(RING);
+
+        assert!(!ring.intersects_masked(Some(&mask), &centre, None));
+    }
+
+    #[test]
+    fn test_masked_solid_pixel_hits() {
+        let ring = CollisionRectangle::new(Vector2::new(10.0, 10.0), Vector2::new(4.0, 4.0));
+        let edge = CollisionRectangle::new(Vector2::new(12.5, 8.0), Vector2::new(1.0, 3.0));
+        let mask = CollisionMask::new(RING);
+
+        assert!(ring.intersects_masked(Some(&mask), &edge, None));
+    }
+
+    #[test]
+    fn test_masked_both_masks() {
+        let ring1 = CollisionRectangle::new(Vector2::new(0.0, 0.0), Vector2::new(4.0, 4.0));
+        let ring2 = CollisionRectangle::new(Vector2::new(3.0, 3.0), Vector2::new(4.0, 4.0));
+        let ring3 = CollisionRectangle::new(Vector2::new(3.0, 0.0), Vector2::new(4.0, 4.0));
+        let mask = CollisionMask::new(RING);
+
+        // Only transparent corners overlap
+        assert!(!ring1.intersects_masked(Some(&mask), &ring2, Some(&mask)));
+        // Right column of ring1 lands on the left column of ring3
+        assert!(ring1.intersects_masked(Some(&mask), &ring3, Some(&mask)));
+    }
+
+    #[test]
+    fn test_masked_without_masks_is_aabb() {
+        let rect1 = CollisionRectangle::new(Vector2::new(0.0, 0.0), Vector2::new(10.0, 10.0));
+        let rect2 = CollisionRectangle::new(Vector2::new(9.5, 9.5), Vector2::new(10.0, 10.0));
+
+        assert!(rect1.intersects_masked(None, &rect2, None));
+    }
+
+    #[test]
+    fn test_sweep_fast_rectangle_tunnels_without_sweep() {
+        let hook = CollisionRectangle::new(Vector2::new(0.0, 20.0), Vector2::new(5.0, 3.0));
+        let creep = CollisionRectangle::new(Vector2::new(0.0, 10.0), Vector2::new(13.0, 2.0));
+        let velocity = Vector2::new(0.0, -15.0);
+
+        assert!(!hook.moved(velocity).intersects(&creep)); // Jumped over it
+        let toi = hook.sweep(velocity, &creep, Vector2::zeros()).unwrap();
+        assert!((toi - 8.0 / 15.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_sweep_both_moving() {
+        let hook = CollisionRectangle::new(Vector2::new(20.0, 20.0), Vector2::new(5.0, 3.0));
+        let creep = CollisionRectangle::new(Vector2::new(0.0, 10.0), Vector2::new(13.0, 11.0));
+
+        // Creep walks into the hook's column while the hook flies up
+        let toi = hook
+            .sweep(Vector2::new(0.0, -2.0), &creep, Vector2::new(10.0, 0.0))
+            .unwrap();
+        assert!((toi - 0.7).abs() < 1e-6);
+        // Creep walking away is never caught
+        assert!(hook
+            .sweep(Vector2::new(0.0, -2.0), &creep, Vector2::new(-10.0, 0.0))
+            .is_none());
+    }
+
+    #[test]
+    fn test_sweep_already_overlapping() {
+        let rect1 = CollisionRectangle::new(Vector2::new(0.0, 0.0), Vector2::new(10.0, 10.0));
+        let rect2 = CollisionRectangle::new(Vector2::new(5.0, 5.0), Vector2::new(10.0, 10.0));
+
+        assert_eq!(
+            rect1.sweep(Vector2::new(-3.0, 0.0), &rect2, Vector2::zeros()),
+            Some(0.0)
+        );
+    }
+
+    #[test]
+    fn test_sweep_misses() {
+        let rect1 = CollisionRectangle::new(Vector2::new(0.0, 20.0), Vector2::new(5.0, 3.0));
+        let rect2 = CollisionRectangle::new(Vector2::new(10.0, 10.0), Vector2::new(5.0, 5.0));
+
+        // Parallel, one column over
+        assert!(rect1
+            .sweep(Vector2::new(0.0, -20.0), &rect2, Vector2::zeros())
+            .is_none());
+        // Too slow to get there this tick
+        assert!(rect1
+            .sweep(Vector2::new(10.0, -2.0), &rect2, Vector2::zeros())
+            .is_none());
+    }
+
+    #[test]
+    fn test_sweep_masked_passes_transparent_pixels() {
+        let ring = CollisionRectangle::new(Vector2::new(10.0, 0.0), Vector2::new(4.0, 4.0));
+        let mask = CollisionMask::new(RING);
+        // Column 0 of the ring is only lit in the middle rows
+        let corner = CollisionRectangle::new(Vector2::new(10.0, 10.0), Vector2::new(1.0, 1.0));
+
+        let aabb_toi = corner
+            .sweep(Vector2::new(0.0, -8.0), &ring, Vector2::zeros())
+            .unwrap();
+        let masked_toi = corner
+            .sweep_masked(
+                None,
+                Vector2::new(0.0, -8.0),
+                &ring,
+                Some(&mask),
+                Vector2::zeros(),
+            )
+            .unwrap();
+        assert!(masked_toi > aabb_toi);
+        // Lands on a lit pixel
+        assert!(corner
+            .moved(Vector2::new(0.0, -8.0 * masked_toi))
+            .intersects_masked(None, &ring, Some(&mask)));
+    }
+
+    #[test]
+    fn test_distance_to_point() {
+        let rect = CollisionRectangle::new(Vector2::new(10.0, 10.0), Vector2::new(10.0, 5.0));
+
+        assert_eq!(rect.distance_to(Vector2::new(15.0, 12.0)), 0.0); // Inside
+        assert_eq!(rect.distance_to(Vector2::new(15.0, 25.0)), 10.0); // Straight below
+        assert_eq!(rect.distance_to(Vector2::new(23.0, 19.0)), 5.0); // Off the corner
+    }
 }
@@ -0,0 +1,182 @@
+use core::fmt::Write;
+
+use embedded_graphics::{
+    prelude::{Point, Primitive},
+    primitives::Rectangle,
+    text::{Alignment, Text},
+};
+use fugit::MicrosDurationU64;
+
+use crate::graphics::{
+    display::{get_fps, DisplayEnum, DISPLAY_HEIGHT, DISPLAY_WIDTH},
+    image::{draw_primitive, draw_text},
+    resources::{TEXT_STYLE, WHITE_LINE},
+    utils::TextBuffer,
+};
+
+use super::{collisions::CollisionRectangle, spawner::Spawner, Instant};
+
+const LINE_HEIGHT: i32 = 6;
+const WINDOW_MS: u64 = 1000;
+
+// Frame and tick rates are averaged over a one second window
+pub struct DebugOverlay {
+    enabled: bool,
+    window_start: Option<Instant>,
+    frames: u32,
+    ticks: u32,
+    fps: u32,
+    tps: u32,
+    flush: Option<MicrosDurationU64>,
+    hitboxes: bool,
+}
+
+impl DebugOverlay {
+    pub fn new() -> Self {
+        DebugOverlay {
+            enabled: cfg!(feature = "debug-overlay"),
+            window_start: None,
+            frames: 0,
+            ticks: 0,
+            fps: 0,
+            tps: 0,
+            flush: None,
+            hitboxes: false,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+    }
+
+    pub fn set_hitboxes(&mut self, visible: bool) {
+        self.hitboxes = visible;
+    }
+
+    pub fn hitboxes(&self) -> bool {
+        self.hitboxes
+    }
+
+    pub fn frame(&mut self, time: Instant) {
+        let window_start = *self.window_start.get_or_insert(time);
+        self.frames += 1;
+        let Some(elapsed) = time.checked_duration_since(window_start) else {
+            self.window_start = Some(time);
+            return;
+        };
+        if elapsed.to_millis() < WINDOW_MS {
+            return;
+        }
+        self.fps = get_fps(elapsed / self.frames);
+        self.tps = (self.ticks as u64 * 1000 / elapsed.to_millis()) as u32;
+        self.window_start = Some(time);
+        self.frames = 0;
+        self.ticks = 0;
+    }
+
+    pub fn tick(&mut self) {
+        self.ticks += 1;
+    }
+
+    pub fn report_flush(&mut self, duration: MicrosDurationU64) {
+        self.flush = Some(duration);
+    }
+
+    pub fn draw(&self, display: &mut DisplayEnum, objects: usize, spawner: Option<&Spawner>) {
+        if !self.enabled {
+            return;
+        }
+        let mut lines: [TextBuffer<20>; 4] = [
+            TextBuffer::new(),
+            TextBuffer::new(),
+            TextBuffer::new(),
+            TextBuffer::new(),
+        ];
+        let _ = write!(lines[0], "FPS {} TPS {}", self.fps, self.tps);
+        let _ = write!(lines[1], "OBJ {}", objects);
+        if let Some(spawner) = spawner {
+            let _ = write!(
+                lines[2],
+                "SPD {:.2} RATE {}",
+                spawner.base_speed(),
+                spawner.spawn_rate_ms()
+            );
+        }
+        if let Some(flush) = self.flush {
+            let _ = write!(lines[3], "I2C {}us", flush.to_micros());
+        }
+
+        // Middle of the right edge, the top and bottom rows belong to the
+        // HUDs of both players in versus
+        let count = lines
+            .iter()
+            .filter(|line| !line.as_str().is_empty())
+            .count() as i32;
+        let mut y = (DISPLAY_HEIGHT as i32 + count * LINE_HEIGHT) / 2 - 1;
+        for line in lines.iter().rev().filter(|line| !line.as_str().is_empty()) {
+            let text = Text::with_alignment(
+                line.as_str(),
+                Point::new(DISPLAY_WIDTH as i32 - 1, y),
+                TEXT_STYLE,
+                Alignment::Right,
+            );
+            draw_text(display, text);
+            y -= LINE_HEIGHT;
+        }
+    }
+
+    pub fn draw_hitboxes<I>(&self, display: &mut DisplayEnum, boxes: I)
+    where
+        I: IntoIterator<Item = CollisionRectangle>,
+    {
+        if !self.hitboxes {
+            return;
+        }
+        for collision_box in boxes {
+            let outline = Rectangle::from(&collision_box).into_styled(WHITE_LINE);
+            draw_primitive(display, &outline);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics::{mock_display::MockDisplay, pixelcolor::BinaryColor, prelude::*};
+
+    use super::*;
+    use crate::game::{object::GameObject, Creep, Game, GamePoint, Mode};
+
+    fn draw_frame(game: &mut Game) -> MockDisplay<BinaryColor> {
+        let mut mock = MockDisplay::new();
+        mock.set_allow_overdraw(true);
+        mock.set_allow_out_of_bounds_drawing(true);
+        let mut display = DisplayEnum::Mock(mock);
+        game.draw(&mut display);
+        let DisplayEnum::Mock(mock) = display else {
+            unreachable!()
+        };
+        mock
+    }
+
+    #[test]
+    fn test_hitbox_frame_adds_only_outlines() {
+        let mut game = Game::default();
+        game.start_run(Mode::Classic);
+        let creep = GameObject::make_creep(GamePoint::new(10, 20), Creep::Radiant, 0.0);
+        let outline = Rectangle::from(&creep.collision_box());
+        game.object_handler.insert(creep).unwrap();
+
+        let plain = draw_frame(&mut game);
+        game.debug_overlay.set_hitboxes(true);
+        let with_hitboxes = draw_frame(&mut game);
+
+        for point in with_hitboxes.bounding_box().points() {
+            let on_outline = outline.contains(point) && !outline.offset(-1).contains(point);
+            if on_outline {
+                assert_eq!(with_hitboxes.get_pixel(point), Some(BinaryColor::On));
+            } else {
+                assert_eq!(with_hitboxes.get_pixel(point), plain.get_pixel(point));
+            }
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use core::fmt;
+
+#[cfg(target_os = "none")]
+use defmt::Format;
+
+use super::Scene;
+use crate::controls::Player;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameError {
+    // Score and damage only apply while a run is played
+    NoRun(Scene),
+    // Player two only has a score and HP in versus
+    NotPlaying(Player),
+    // The platform clock reported an earlier time than the last frame,
+    // the game resyncs to it and skips the tick
+    ClockWentBackwards,
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameError::NoRun(scene) => write!(f, "not in a game ({})", scene.to_str()),
+            GameError::NotPlaying(player) => write!(f, "{:?} isn't playing", player),
+            GameError::ClockWentBackwards => write!(f, "clock went backwards"),
+        }
+    }
+}
+
+#[cfg(target_os = "none")]
+impl Format for GameError {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            GameError::NoRun(scene) => defmt::write!(f, "not in a game ({})", scene.to_str()),
+            GameError::NotPlaying(player) => defmt::write!(f, "{} isn't playing", player),
+            GameError::ClockWentBackwards => defmt::write!(f, "clock went backwards"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Game, Instant};
+
+    #[test]
+    fn test_clock_going_backwards_skips_a_tick() {
+        let mut game = Game::default();
+        assert!(game.process(Instant::from_ticks(1_000_000)).is_ok());
+        assert_eq!(
+            game.process(Instant::from_ticks(500_000)),
+            Err(GameError::ClockWentBackwards)
+        );
+        assert!(game.process(Instant::from_ticks(600_000)).is_ok());
+    }
+}
@@ -0,0 +1,126 @@
+use super::{Hookable, Hp, Id, Scene, Score, StageNr};
+use crate::controls::Player;
+
+const EVENT_CAPACITY: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameEvent {
+    Spawned(Id, Hookable),
+    Hooked(Id, Hookable),
+    Reeled(Option<Hookable>),
+    Missed(Player),
+    Escaped(Id, Hookable),
+    Damaged(Player, Hp),
+    Scored(Player, Score),
+    StageReached(StageNr),
+    SceneChanged(Scene, Scene),
+}
+
+// Fixed size so the firmware doesn't need the heap for it.
+// Events published past capacity are counted in `dropped` instead of stored.
+pub struct EventQueue {
+    events: [Option<GameEvent>; EVENT_CAPACITY],
+    len: usize,
+    dropped: u16,
+}
+
+impl EventQueue {
+    pub fn new() -> Self {
+        EventQueue {
+            events: [None; EVENT_CAPACITY],
+            len: 0,
+            dropped: 0,
+        }
+    }
+
+    pub fn publish(&mut self, event: GameEvent) {
+        if self.len == EVENT_CAPACITY {
+            self.dropped = self.dropped.saturating_add(1);
+            return;
+        }
+        self.events[self.len] = Some(event);
+        self.len += 1;
+    }
+
+    pub fn dropped(&self) -> u16 {
+        self.dropped
+    }
+
+    // Whatever the consumer doesn't take is dropped with the iterator
+    pub fn drain(&mut self) -> Drain<'_> {
+        Drain {
+            queue: self,
+            next: 0,
+        }
+    }
+}
+
+pub struct Drain<'a> {
+    queue: &'a mut EventQueue,
+    next: usize,
+}
+
+impl Iterator for Drain<'_> {
+    type Item = GameEvent;
+
+    fn next(&mut self) -> Option<GameEvent> {
+        if self.next >= self.queue.len {
+            return None;
+        }
+        self.next += 1;
+        self.queue.events[self.next - 1].take()
+    }
+}
+
+impl Drop for Drain<'_> {
+    fn drop(&mut self) {
+        for event in &mut self.queue.events[self.next..self.queue.len] {
+            *event = None;
+        }
+        self.queue.len = 0;
+        self.queue.dropped = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drain_hands_out_events_in_order() {
+        let mut queue = EventQueue::new();
+        queue.publish(GameEvent::StageReached(1));
+        queue.publish(GameEvent::Missed(Player::One));
+        let events: heapless::Vec<GameEvent, EVENT_CAPACITY> = queue.drain().collect();
+        assert_eq!(
+            events,
+            [GameEvent::StageReached(1), GameEvent::Missed(Player::One)]
+        );
+        assert_eq!(queue.drain().next(), None);
+    }
+
+    #[test]
+    fn test_partial_drain_leaves_nothing_stale() {
+        let mut queue = EventQueue::new();
+        for stage in 1..=3 {
+            queue.publish(GameEvent::StageReached(stage));
+        }
+        assert_eq!(queue.drain().next(), Some(GameEvent::StageReached(1)));
+
+        queue.publish(GameEvent::StageReached(4));
+        let mut drain = queue.drain();
+        assert_eq!(drain.next(), Some(GameEvent::StageReached(4)));
+        assert_eq!(drain.next(), None);
+    }
+
+    #[test]
+    fn test_overflow_is_counted_until_drained() {
+        let mut queue = EventQueue::new();
+        for _ in 0..EVENT_CAPACITY + 2 {
+            queue.publish(GameEvent::Missed(Player::Two));
+        }
+        assert_eq!(queue.dropped(), 2);
+        assert_eq!(queue.drain().count(), EVENT_CAPACITY);
+        assert_eq!(queue.dropped(), 0);
+    }
+}
@@ -0,0 +1,65 @@
+use core::fmt::{self, Write};
+
+use embedded_graphics::{
+    geometry::Point,
+    text::{Alignment, Text},
+};
+use heapless::Vec;
+
+use super::TickCount;
+use crate::graphics::{
+    display::DisplayEnum, image::draw_text, resources::TEXT_STYLE, utils::TextBuffer,
+};
+
+const MAX_FLOATING_TEXTS: usize = 4;
+const LIFETIME_TICKS: TickCount = 45;
+const RISE_EVERY_TICKS: TickCount = 3;
+
+struct FloatingText {
+    text: TextBuffer<12>,
+    location: Point,
+    age: TickCount,
+}
+
+// Short texts drifting up from where something happened, e.g. hit scores
+#[derive(Default)]
+pub struct FloatingTexts {
+    texts: Vec<FloatingText, MAX_FLOATING_TEXTS>,
+}
+
+impl FloatingTexts {
+    // Pushes out the oldest one when full
+    pub fn push(&mut self, location: Point, args: fmt::Arguments) {
+        let mut text = TextBuffer::new();
+        let _ = text.write_fmt(args);
+        if self.texts.is_full() {
+            self.texts.remove(0);
+        }
+        let _ = self.texts.push(FloatingText {
+            text,
+            location,
+            age: 0,
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.texts.clear();
+    }
+
+    pub fn tick(&mut self) {
+        for text in self.texts.iter_mut() {
+            text.age += 1;
+        }
+        self.texts.retain(|text| text.age < LIFETIME_TICKS);
+    }
+
+    pub fn draw(&self, display: &mut DisplayEnum) {
+        for text in self.texts.iter() {
+            let rise = (text.age / RISE_EVERY_TICKS) as i32;
+            let location = text.location - Point::new(0, rise);
+            let text =
+                Text::with_alignment(text.text.as_str(), location, TEXT_STYLE, Alignment::Center);
+            draw_text(display, text);
+        }
+    }
+}
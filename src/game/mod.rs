@@ -1,22 +1,58 @@
+mod animation;
+mod bot;
 mod collisions;
+mod debug;
+mod error;
+mod events;
+mod floating_text;
+mod modes;
 mod object;
 mod pudge;
+mod scenes;
+mod scoring;
 mod spawner;
+mod stages;
+#[cfg(test)]
+pub(crate) mod testing;
+mod waves;
 
-use embedded_graphics::image::Image;
+pub use bot::Bot;
+use core::fmt::Write;
+use debug::DebugOverlay;
 use embedded_graphics::prelude::*;
+use embedded_graphics::text::{Alignment, Baseline, TextStyleBuilder};
 use embedded_graphics::{geometry::Point, text::Text};
-use fugit::TimerInstantU64;
+pub use error::GameError;
+use events::EventQueue;
+pub use events::GameEvent;
+use floating_text::FloatingTexts;
+use fugit::{MicrosDurationU64, TimerInstantU64};
+use modes::HighScores;
+pub use modes::{Mode, MODES};
 use nalgebra::Vector2;
+pub use object::Id;
 use object::{GameObject, GameObjectSignal, ObjectHandler};
-use pudge::{Pudge, PudgeSignal};
-use spawner::Spawner;
+use pudge::{Pudge, PudgeSignal, Side};
+pub use scenes::{Run, Scene};
+use scenes::{SceneStack, VERSUS_START};
+use scoring::Combo;
+pub use scoring::ScoreWeights;
+use spawner::{Spawner, SpawnerBuilder};
+pub use spawner::{SpawnerError, SpawnerTweaks};
+use stages::Stage;
+pub use stages::StageNr;
+pub use waves::{parse_waves, Wave, WaveError};
+use waves::{WaveTable, DEFAULT_WAVES};
 
-use crate::graphics::image::{draw_hp, draw_image, draw_text};
-use crate::graphics::resources::SPLASH;
+use crate::graphics::image::{draw_hp, draw_text};
+use crate::graphics::resources::{CREEP_WIDTH, DIRE_CREEP_WIDTH, SCREEN_WIDTH};
+use crate::graphics::utils::TextBuffer;
 use crate::{
-    controls::ControlEnum,
-    graphics::{display::DisplayEnum, resources::TEXT_STYLE},
+    controls::{ControlEnum, Player},
+    graphics::{
+        display::DisplayEnum,
+        resources::{CHAR_WIDTH, TEXT_STYLE},
+    },
 };
 #[cfg(target_os = "none")]
 use libm::floorf;
@@ -25,8 +61,12 @@ use libm::floorf;
 const TICK_RATE: u64 = 1000;
 #[cfg(not(target_arch = "wasm32"))]
 const TICK_RATE: u64 = 16_000;
+// At most one logic tick per `process` call this far apart
+pub const TICK: MicrosDurationU64 = MicrosDurationU64::from_ticks(TICK_RATE);
+const STAGE_BANNER_DURATION: MicrosDurationU64 = MicrosDurationU64::secs(2);
+// Idle time on the title scene before the bot starts a demo run
+pub const ATTRACT_DELAY: MicrosDurationU64 = MicrosDurationU64::secs(20);
 
-type Id = u16;
 type TickCount = u32;
 type Speed = f32;
 
@@ -79,7 +119,7 @@ impl Into<Point> for GamePoint {
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
-enum RuneEnum {
+pub enum RuneEnum {
     WalkSpeed(i32),
     HookSpeed(i32),
     HookSize(i32),
@@ -92,232 +132,666 @@ pub enum Creep {
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
-enum Hookable {
+pub enum Hookable {
     Creep(Creep),
     Blockade,
     Rune(RuneEnum),
 }
 
-enum GameDifficultyEnum {
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum GameDifficultyEnum {
     Easy,
     Medium,
     Hard,
     Dendi,
 }
 
+// In the order the settings scene steps through them
+const DIFFICULTIES: [GameDifficultyEnum; 4] = [
+    GameDifficultyEnum::Easy,
+    GameDifficultyEnum::Medium,
+    GameDifficultyEnum::Hard,
+    GameDifficultyEnum::Dendi,
+];
+
+impl GameDifficultyEnum {
+    fn name(&self) -> &'static str {
+        match self {
+            GameDifficultyEnum::Easy => "EASY",
+            GameDifficultyEnum::Medium => "MEDIUM",
+            GameDifficultyEnum::Hard => "HARD",
+            GameDifficultyEnum::Dendi => "DENDI",
+        }
+    }
+
+    fn index(&self) -> usize {
+        DIFFICULTIES.iter().position(|d| d == self).unwrap_or(0)
+    }
+
+    // Stays on the hardest one
+    fn harder(&self) -> Self {
+        DIFFICULTIES[(self.index() + 1).min(DIFFICULTIES.len() - 1)]
+    }
+
+    fn easier(&self) -> Self {
+        DIFFICULTIES[self.index().saturating_sub(1)]
+    }
+}
+
 pub struct Game {
-    state: GameState,
+    scenes: SceneStack,
+    // For the scene on top when Pudge doesn't walk in it
+    scene_input: Option<ControlEnum>,
     time: Option<Instant>,
     seed: u64,
-    pudge: Pudge,
+    // Indexed by `Player::index`, player two only plays in versus
+    pudges: [Pudge; 2],
     object_handler: ObjectHandler,
     spawner: Option<Spawner>,
+    spawner_tweaks: SpawnerTweaks,
+    stage_banner_until: Option<Instant>,
+    waves: WaveTable,
+    difficulty: GameDifficultyEnum,
+    score_weights: ScoreWeights,
+    combos: [Combo; 2],
+    floating_texts: FloatingTexts,
+    events: EventQueue,
+    debug_overlay: DebugOverlay,
+    // Plays player one while the demo runs
+    attract: Option<Bot>,
+    // Last real input, or the first tick
+    idle_since: Option<Instant>,
+    // Of the selectors on screen, indexed like `SELECTORS`
+    selector_ids: [Option<Id>; SELECTORS.len()],
+    selected: Option<Selection>,
+    // Time attack's deadline
+    run_ends_at: Option<Instant>,
+    paused_since: Option<Instant>,
+    high_scores: HighScores,
+    // The run that just ended set its mode's high score
+    new_best: bool,
 }
 
-type Score = i32;
-type Hp = u8;
-
-type Started = bool;
-
-#[derive(Clone, Copy, PartialEq, Eq)]
-enum GameState {
-    Init(Started),
-    Hookin(Score, Hp),
-    GameOver(Started, Score),
+// What hooking a selector starts
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Selection {
+    Run(Mode),
+    Versus,
 }
 
-impl GameState {
-    pub fn next(&mut self) {
-        match self.clone() {
-            GameState::Init(_) => *self = GameState::Hookin(0, 3),
-            GameState::Hookin(score, _) => *self = GameState::GameOver(false, score.clone()),
-            GameState::GameOver(_, _) => *self = GameState::Hookin(0, 3),
-        }
-    }
-    pub fn set_started(&mut self) {
-        match self.clone() {
-            GameState::Init(false) => *self = GameState::Init(true),
-            GameState::GameOver(false, score) => *self = GameState::GameOver(true, score),
-            _ => {}
-        }
-    }
-    pub fn add_score(&mut self, points: i16) {
-        if let GameState::Hookin(score, hp) = self.clone() {
-            if points < 0 && (-points) as i32 > score {
-                *self = GameState::Hookin(0, hp);
-                return;
-            }
-            *self = GameState::Hookin(score + points as i32, hp);
-        } else {
-            panic!("Can't add score to non-active game");
+impl Selection {
+    fn creep(&self) -> Creep {
+        match self {
+            Selection::Run(_) => Creep::Radiant,
+            Selection::Versus => Creep::Dire,
         }
     }
 
-    pub fn damage(&mut self) {
-        if let GameState::Hookin(score, hp) = self.clone() {
-            if hp == 1 {
-                self.next();
-                return;
-            }
-            *self = GameState::Hookin(score, hp - 1);
-        } else {
-            panic!("Can't damage non-active game");
+    fn width(&self) -> i32 {
+        match self {
+            Selection::Run(_) => CREEP_WIDTH as i32,
+            Selection::Versus => DIRE_CREEP_WIDTH as i32,
         }
     }
 
-    pub fn to_str(&self) -> &str {
+    fn label(&self) -> &'static str {
         match self {
-            GameState::GameOver(_, _) => "Game Over",
-            GameState::Init(_) => "Game start",
-            GameState::Hookin(_, _) => "Game in progress",
+            Selection::Run(mode) => mode.label(),
+            Selection::Versus => "VS",
         }
     }
 }
 
+// Creeps standing on the lane of the menu and game over scenes, by x
+const SELECTORS: [(i32, Selection); 4] = [
+    (0, Selection::Run(Mode::Classic)),
+    (40, Selection::Run(Mode::TimeAttack)),
+    (80, Selection::Run(Mode::SuddenDeath)),
+    (
+        SCREEN_WIDTH as i32 - DIRE_CREEP_WIDTH as i32,
+        Selection::Versus,
+    ),
+];
+const SELECTOR_Y: i32 = 24;
+
+type Score = i32;
+type Hp = u8;
+
 impl Default for Game {
     fn default() -> Self {
         return Game {
-            state: GameState::Init(false),
+            scenes: SceneStack::new(Scene::Title),
+            scene_input: None,
             spawner: None,
+            spawner_tweaks: SpawnerTweaks::default(),
+            stage_banner_until: None,
+            waves: WaveTable::from_slice(&DEFAULT_WAVES).unwrap(),
+            difficulty: GameDifficultyEnum::Medium,
+            score_weights: ScoreWeights::for_difficulty(GameDifficultyEnum::Medium),
+            combos: Default::default(),
+            floating_texts: FloatingTexts::default(),
             time: None,
             seed: 0,
-            pudge: Pudge::default(),
+            pudges: [Pudge::new(Side::Bottom), Pudge::new(Side::Top)],
             object_handler: ObjectHandler::new(),
+            events: EventQueue::new(),
+            debug_overlay: DebugOverlay::new(),
+            attract: None,
+            idle_since: None,
+            selector_ids: [None; SELECTORS.len()],
+            selected: None,
+            run_ends_at: None,
+            paused_since: None,
+            high_scores: HighScores::default(),
+            new_best: false,
         };
     }
 }
 impl Game {
+    // A clean world: fresh Pudges, nothing on the lanes and nothing spawning
     pub fn init(&mut self) {
         //TODO: difficulty scaling
-        self.pudge = Pudge::default();
-        self.spawner = Some(Spawner::new(1, 123489, 0.65));
+        self.pudges = [Pudge::new(Side::Bottom), Pudge::new(Side::Top)];
+        self.object_handler.clear();
+        // Set up by `start_stage` once the run begins
+        self.spawner = None;
+        self.stage_banner_until = None;
+        self.run_ends_at = None;
+        for combo in &mut self.combos {
+            combo.reset();
+        }
+        self.floating_texts.clear();
+    }
+
+    // Swaps in the spawner of the stage being played and shows its banner.
+    // Versus stays on the first stage with creeps for both sides.
+    fn start_stage(&mut self) {
+        self.spawner = self.stage_spawner();
+        if let Scene::Playing(Run::Solo(_, _, _, stage)) = self.scene() {
+            self.stage_banner_until = self.time.map(|time| time + STAGE_BANNER_DURATION);
+            self.events.publish(GameEvent::StageReached(stage));
+        }
+    }
+
+    fn stage_spawner(&self) -> Option<Spawner> {
+        let builder = match self.scene() {
+            Scene::Playing(Run::Solo(_, _, _, stage)) => Stage::get(stage)
+                .spawner(&self.waves)
+                .seed(self.seed.wrapping_add(123489 + stage as u64)),
+            Scene::Playing(Run::Versus(_, _)) => Stage::get(1)
+                .spawner(&self.waves)
+                .creep_weights(1, 1)
+                .seed(self.seed.wrapping_add(123489)),
+            _ => return None,
+        };
+        self.spawner_tweaks.apply(builder).build().ok()
+    }
+
+    // Nothing spawns while it's up
+    fn showing_stage_banner(&self) -> bool {
+        match (self.time, self.stage_banner_until) {
+            (Some(time), Some(until)) => time < until,
+            _ => false,
+        }
     }
 
+    // Used from the next `init`
+    pub fn set_waves(&mut self, waves: &[Wave]) -> Result<(), SpawnerError> {
+        SpawnerBuilder::new().waves(waves).build()?;
+        self.waves = waves.iter().copied().collect();
+        Ok(())
+    }
+
+    // Spawns follow the seed, peers playing online share it
     pub fn set_seed(&mut self, seed: u64) {
         self.seed = seed;
     }
 
-    pub fn process(&mut self, new_time: Instant) {
-        if self.time.is_none() {
-            self.time = Some(new_time.clone());
+    // Takes effect right away, a running stage starts its waves over
+    pub fn set_spawner_tweaks(&mut self, tweaks: SpawnerTweaks) -> Result<(), SpawnerError> {
+        tweaks.apply(Stage::get(1).spawner(&self.waves)).build()?;
+        self.spawner_tweaks = tweaks;
+        if self.spawner.is_some() {
+            self.spawner = self.stage_spawner();
+        }
+        Ok(())
+    }
+
+    pub fn score_weights(&self) -> ScoreWeights {
+        self.score_weights
+    }
+
+    pub fn set_score_weights(&mut self, weights: ScoreWeights) {
+        self.score_weights = weights;
+    }
+
+    // Replaces the score weights with the difficulty's
+    pub(crate) fn set_difficulty(&mut self, difficulty: GameDifficultyEnum) {
+        self.difficulty = difficulty;
+        self.score_weights = ScoreWeights::for_difficulty(difficulty);
+    }
+
+    fn run_mut(&mut self) -> Result<&mut Run, GameError> {
+        let scene = self.scene();
+        self.scenes.run_mut().ok_or(GameError::NoRun(scene))
+    }
+
+    // Points from outside the rules, no `Scored` event
+    pub fn add_score(&mut self, player: Player, points: Score) -> Result<(), GameError> {
+        self.run_mut()?.add_score(player, points)
+    }
+
+    // Ends a run or match as if HP ran out, the leader wins a versus match
+    pub fn end_run(&mut self) {
+        if let Scene::Playing(run) = self.scene() {
+            let previous_scene = self.scene();
+            self.switch_scene(Scene::GameOver(run));
+            self.events
+                .publish(GameEvent::SceneChanged(previous_scene, self.scene()));
         }
-        let delta = new_time.checked_duration_since(self.time.unwrap()).unwrap();
+    }
+
+    // Best score of `mode` since power on
+    pub fn high_score(&self, mode: Mode) -> Score {
+        self.high_scores.best(mode)
+    }
+
+    pub fn in_attract_mode(&self) -> bool {
+        self.attract.is_some()
+    }
+
+    fn pudge(&self, player: Player) -> &Pudge {
+        &self.pudges[player.index()]
+    }
+
+    // Skips the menu, for the attract mode and headless runs
+    pub fn start_run(&mut self, mode: Mode) {
+        self.reset_scenes(Scene::Playing(Run::solo(mode)));
+    }
+
+    // Skips the menu, for online matches
+    pub fn start_versus(&mut self) {
+        self.reset_scenes(Scene::Playing(VERSUS_START));
+    }
+
+    // Plays the run or match on screen, or the one just over, again from the start
+    pub fn restart(&mut self) {
+        let run = [Some(self.scene()), self.scenes.below_top()]
+            .into_iter()
+            .flatten()
+            .find_map(|scene| scene.run());
+        match run {
+            Some(Run::Solo(mode, _, _, _)) => self.start_run(mode),
+            Some(Run::Versus(_, _)) => self.start_versus(),
+            None => {}
+        }
+    }
+
+    // Entering the playing scene, whatever came before
+    fn run_started(&mut self, run: Run) {
+        self.init();
+        let now = self.time.unwrap_or(Instant::from_ticks(0));
+        self.run_ends_at = run
+            .mode()
+            .and_then(|mode| mode.time_limit())
+            .map(|limit| now + limit);
+        self.start_stage();
+    }
+
+    // Leaving the playing scene, for game over, a restart or the title.
+    // Drops the run's creeps and whatever Pudge still has on the hook.
+    fn run_ended(&mut self) {
+        self.init();
+        // Each run after it gets different spawns
+        self.seed += 73_432;
+    }
+
+    // The run is over once HP runs out
+    fn finish_run(&mut self) {
+        if let Scene::Playing(run) = self.scene() {
+            self.switch_scene(Scene::GameOver(run));
+        }
+    }
+
+    // A clock that goes backwards only costs the current tick
+    pub fn process(&mut self, new_time: Instant) -> Result<(), GameError> {
+        let last_time = *self.time.get_or_insert(new_time);
         self.time = Some(new_time);
+        self.debug_overlay.frame(new_time);
+        let Some(delta) = new_time.checked_duration_since(last_time) else {
+            return Err(GameError::ClockWentBackwards);
+        };
 
         if delta.to_micros() < TICK_RATE {
-            return;
+            return Ok(());
         }
+        self.debug_overlay.tick();
+        self.floating_texts.tick();
 
-        match self.state {
-            GameState::Init(_) => self.selector_tick(),
-            GameState::Hookin(_, _) => self.main_tick(),
-            GameState::GameOver(_, _) => self.selector_tick(),
+        let previous_scene = self.scene();
+        self.scene_input_tick();
+        self.attract_tick(new_time);
+        let result = match self.scene() {
+            Scene::Menu | Scene::GameOver(_) => {
+                self.selector_tick();
+                Ok(())
+            }
+            Scene::Playing(_) => self.main_tick(new_time),
+            Scene::Title | Scene::Paused | Scene::HighScores | Scene::Settings => Ok(()),
+        };
+        if self.scene() != previous_scene {
+            self.events
+                .publish(GameEvent::SceneChanged(previous_scene, self.scene()));
         }
+        result
     }
-    fn selector_tick(&mut self) {
-        match self.state {
-            GameState::Init(false) | GameState::GameOver(false, _) => self.insert_selector(),
+
+    // Starts the demo after `ATTRACT_DELAY` on the title scene and goes
+    // back there once the bot's run is over
+    fn attract_tick(&mut self, time: Instant) {
+        let idle_since = *self.idle_since.get_or_insert(time);
+        match (self.attract, self.scene()) {
+            (None, Scene::Title) => {
+                let idle = time.checked_duration_since(idle_since);
+                if idle.is_some_and(|idle| idle >= ATTRACT_DELAY) {
+                    self.attract = Some(Bot::new(Player::One));
+                    self.start_run(Mode::Classic);
+                }
+            }
+            (Some(_), Scene::GameOver(_)) => self.leave_attract(),
             _ => {}
         }
+        if let Some(bot) = self.attract {
+            let control = bot.control(self);
+            self.pudges[bot.player().index()].act(Some(control));
+        }
+    }
+
+    fn leave_attract(&mut self) {
+        self.attract = None;
+        self.idle_since = self.time;
+        self.reset_scenes(Scene::Title);
+    }
 
-        let pudge_signal = self.pudge.tick(&mut self.object_handler);
+    // Events published during the last logical tick
+    pub fn drain_events(&mut self) -> impl Iterator<Item = GameEvent> + '_ {
+        self.events.drain()
+    }
+
+    pub fn dropped_events(&self) -> u16 {
+        self.events.dropped()
+    }
+
+    fn selector_tick(&mut self) {
+        let pudge_signal = self.pudges[Player::One.index()].tick(&mut self.object_handler);
+        match pudge_signal {
+            Some(PudgeSignal::Hooked(ref obj)) => {
+                self.selected = self
+                    .selector_ids
+                    .iter()
+                    .position(|id| *id == Some(obj.id))
+                    .map(|idx| SELECTORS[idx].1);
+                self.events
+                    .publish(GameEvent::Hooked(obj.id, obj.game_type))
+            }
+            Some(PudgeSignal::Reeled(obj)) => self.events.publish(GameEvent::Reeled(obj)),
+            _ => {}
+        }
         if let Some(PudgeSignal::Reeled(Some(_))) = pudge_signal {
-            if let GameState::GameOver(_, _) = self.state {
-                self.seed += 73_432;
-                self.init();
+            let run = match self.selected.take() {
+                Some(Selection::Run(mode)) => Run::solo(mode),
+                Some(Selection::Versus) => VERSUS_START,
+                None => return,
+            };
+            self.switch_scene(Scene::Playing(run));
+        }
+    }
+
+    fn insert_selectors(&mut self) {
+        for (idx, (x, selection)) in SELECTORS.iter().enumerate() {
+            let selector =
+                GameObject::make_creep(GamePoint::new(*x, SELECTOR_Y), selection.creep(), 0.0);
+            let game_type = selector.game_type;
+            self.selector_ids[idx] = self.object_handler.insert(selector).ok();
+            if let Some(id) = self.selector_ids[idx] {
+                self.events.publish(GameEvent::Spawned(id, game_type));
             }
-            self.state.next();
         }
     }
 
-    fn insert_selector(&mut self) {
-        let starter_creep = GameObject::make_creep(0, GamePoint::new(0, 24), Creep::Radiant, 0.0);
-        self.object_handler.insert(starter_creep);
-        self.state.set_started();
+    fn players(&self) -> &'static [Player] {
+        match self.scene() {
+            Scene::Playing(Run::Versus(_, _)) => &[Player::One, Player::Two],
+            _ => &[Player::One],
+        }
     }
 
-    fn main_tick(&mut self) {
-        let pudge_signal = self.pudge.tick(&mut self.object_handler);
-        if let Some(signal) = pudge_signal {
-            match signal {
-                PudgeSignal::Hooked(obj) => {
-                    if obj.game_type == Hookable::Creep(Creep::Radiant) {
-                        let points = obj.calculate_score();
-                        self.state
-                            .add_score(points.try_into().expect("Points too large!"));
-                    }
-                }
-                PudgeSignal::Missed => {
-                    self.state.add_score(-10);
-                }
-                PudgeSignal::Reeled(_obj) => {
-                    //todo
-                }
+    fn main_tick(&mut self, time: Instant) -> Result<(), GameError> {
+        if self.run_ends_at.is_some_and(|ends_at| time >= ends_at) {
+            self.finish_run();
+            return Ok(());
+        }
+        for &player in self.players() {
+            self.player_tick(player)?;
+            // A miss can end the run
+            if !self.scene().is_playing() {
+                return Ok(());
+            }
+        }
+
+        let escaped = self
+            .object_handler
+            .retain_mut(|object| !matches!(object.tick(), Some(GameObjectSignal::OutOfBounds)));
+        for deleted_obj in escaped {
+            self.events
+                .publish(GameEvent::Escaped(deleted_obj.id, deleted_obj.game_type));
+            // Later escapes in the same tick don't count once the game is over
+            if !self.scene().is_playing() {
+                continue;
+            }
+            // Each creep only hurts the player on its side
+            let owner = self.players().iter().copied().find(|player| {
+                let side = self.pudges[player.index()].side();
+                deleted_obj.game_type == Hookable::Creep(side.creep())
+            });
+            if let Some(player) = owner {
+                self.combos[player.index()].reset();
+                self.damage(player)?;
             }
         }
 
-        let mut to_remove: [Option<Id>; 8] = [None; 8];
-        for object in self.object_handler.iter_mut() {
-            let signal = object.tick();
-            if let Some(GameObjectSignal::OutOfBounds(id)) = signal {
-                for obj in to_remove.iter_mut() {
-                    if obj.is_none() {
-                        *obj = Some(id);
-                        break;
-                    }
+        if let Scene::Playing(Run::Solo(_, score, _, stage)) = self.scene() {
+            if Stage::next(stage).is_some_and(|next| score >= next.threshold) {
+                self.run_mut()?.next_stage();
+                self.start_stage();
+            }
+        }
+
+        if self.showing_stage_banner() {
+            return Ok(());
+        }
+        // The spawner holds off while too many objects are alive
+        if let Some(ref mut spawn) = self.spawner {
+            let alive = self.object_handler.len();
+            if let Some(object) = spawn.try_spawn(time, alive) {
+                let game_type = object.game_type;
+                if let Ok(id) = self.object_handler.insert(object) {
+                    self.events.publish(GameEvent::Spawned(id, game_type));
                 }
             }
         }
-        for obj in to_remove.iter() {
-            if let Some(id) = obj {
-                if let Some(deleted_obj) = self.object_handler.remove(*id) {
-                    if deleted_obj.game_type == Hookable::Creep(Creep::Radiant) {
-                        self.state.damage();
-                    }
+        Ok(())
+    }
+
+    fn player_tick(&mut self, player: Player) -> Result<(), GameError> {
+        let pudge = &mut self.pudges[player.index()];
+        let Some(signal) = pudge.tick(&mut self.object_handler) else {
+            return Ok(());
+        };
+        let side = pudge.side();
+        let combo = &mut self.combos[player.index()];
+        match signal {
+            PudgeSignal::Hooked(obj) => {
+                self.events
+                    .publish(GameEvent::Hooked(obj.id, obj.game_type));
+                if obj.game_type == Hookable::Creep(side.creep()) {
+                    let points = combo.hit(
+                        &self.score_weights,
+                        &obj,
+                        side,
+                        &self.object_handler,
+                        &self.pudges[player.index()].body_box(),
+                    );
+                    let points = scoring::to_score(points);
+                    self.run_mut()?.add_score(player, points)?;
+                    self.events.publish(GameEvent::Scored(player, points));
+                    self.floating_texts
+                        .push(obj.location().into(), format_args!("+{}", points));
+                } else {
+                    combo.reset();
                 }
-            } else {
-                break;
+            }
+            PudgeSignal::Missed => {
+                combo.reset();
+                self.events.publish(GameEvent::Missed(player));
+                let penalty = -scoring::to_score(self.score_weights.miss_penalty);
+                self.run_mut()?.add_score(player, penalty)?;
+                self.events.publish(GameEvent::Scored(player, penalty));
+                if self
+                    .run_mut()?
+                    .mode()
+                    .is_some_and(|mode| mode.misses_cost_hp())
+                {
+                    self.damage(player)?;
+                }
+            }
+            PudgeSignal::Reeled(obj) => {
+                self.events.publish(GameEvent::Reeled(obj));
             }
         }
-        if let Some(ref mut spawn) = self.spawner {
-            if let Some(object) = spawn.try_spawn(self.time.unwrap()) {
-                self.object_handler.insert(object);
+        Ok(())
+    }
+
+    // Modes without HP take no damage and publish nothing
+    fn damage(&mut self, player: Player) -> Result<(), GameError> {
+        let run = self.run_mut()?;
+        let Some(_) = run.hp(player) else {
+            return Ok(());
+        };
+        run.damage(player)?;
+        let (hp, over) = (run.hp(player).unwrap_or(0), run.is_over());
+        self.events.publish(GameEvent::Damaged(player, hp));
+        if over {
+            self.finish_run();
+        }
+        Ok(())
+    }
+
+    // Outside of versus both players steer the one Pudge
+    pub fn control(&mut self, player: Player, controls: ControlEnum) {
+        match controls {
+            ControlEnum::Debug => return self.debug_overlay.toggle(),
+            ControlEnum::Hitboxes => {
+                let visible = self.debug_overlay.hitboxes();
+                return self.debug_overlay.set_hitboxes(!visible);
             }
+            ControlEnum::None => return,
+            _ => {}
         }
+        // Any real input ends the demo, and only that
+        self.idle_since = self.time;
+        if self.attract.is_some() {
+            let previous_scene = self.scene();
+            self.leave_attract();
+            self.events
+                .publish(GameEvent::SceneChanged(previous_scene, self.scene()));
+            return;
+        }
+        let pudge = match (self.scene(), controls) {
+            // Online peers can't pause each other
+            (Scene::Playing(Run::Versus(_, _)), ControlEnum::Pause) => return,
+            (Scene::Playing(Run::Versus(_, _)), _) => &mut self.pudges[player.index()],
+            (Scene::Playing(_), ControlEnum::Pause)
+            | (Scene::Title | Scene::Paused | Scene::HighScores | Scene::Settings, _) => {
+                self.scene_input = Some(controls);
+                return;
+            }
+            _ => &mut self.pudges[Player::One.index()],
+        };
+        pudge.act(Some(controls));
     }
 
-    pub fn control(&mut self, controls: ControlEnum) {
-        let _signal = self.pudge.act(Some(controls));
+    // Time the platform spent pushing the last frame to the display
+    pub fn report_flush(&mut self, duration: MicrosDurationU64) {
+        self.debug_overlay.report_flush(duration);
     }
 
     pub fn draw(&mut self, display: &mut DisplayEnum) {
-        match self.state {
-            GameState::Init(_) => {
-                let splash = Image::new(&SPLASH, Point::zero());
-                draw_image(display, splash);
+        self.draw_scenes(display);
+        self.debug_overlay
+            .draw(display, self.object_handler.len(), self.spawner.as_ref());
+    }
+
+    // Pudge and the lanes, over the text of the scene they're in
+    fn draw_world(&mut self, display: &mut DisplayEnum, scene: Scene) {
+        match scene {
+            Scene::Playing(Run::Solo(mode, score, hp, stage)) => {
+                Stage::get(stage).decoration.draw(display);
+                let hp = mode.start_hp().map(|_| hp);
+                self.draw_hud(display, Player::One, score, hp, 4);
+                if self.attract.is_some() {
+                    let demo_text = Text::with_text_style(
+                        "DEMO",
+                        Point::new(64, 4),
+                        TEXT_STYLE,
+                        TextStyleBuilder::new()
+                            .alignment(Alignment::Center)
+                            .baseline(Baseline::Top)
+                            .build(),
+                    );
+                    draw_text(display, demo_text);
+                }
+                if self.showing_stage_banner() {
+                    let mut banner = TextBuffer::<16>::new();
+                    let _ = write!(banner, "STAGE {}", stage);
+                    let banner_text = Text::with_text_style(
+                        banner.as_str(),
+                        Point::new(64, 25),
+                        TEXT_STYLE,
+                        TextStyleBuilder::new()
+                            .alignment(Alignment::Center)
+                            .baseline(Baseline::Middle)
+                            .build(),
+                    );
+                    draw_text(display, banner_text);
+                }
             }
-            GameState::Hookin(score, hp) => {
-                let mut buffer = itoa::Buffer::new();
-                let score_str = buffer.format(score);
+            Scene::GameOver(Run::Solo(mode, score, _, stage)) => {
+                let mut buffer = TextBuffer::<24>::new();
+                let _ = write!(buffer, "{} STAGE {}", score, stage);
                 let score_text = Text::with_baseline(
-                    score_str,
-                    Point::new(2, 4),
+                    buffer.as_str(),
+                    Point::new(64, 4),
                     TEXT_STYLE.clone(),
                     embedded_graphics::text::Baseline::Top,
                 );
                 draw_text(display, score_text);
-                draw_hp(display, hp);
-            }
-            GameState::GameOver(_, score) => {
-                let mut buffer = itoa::Buffer::new();
-                let score_str = buffer.format(score);
-                let score_text = Text::with_baseline(
-                    score_str,
-                    Point::new(64, 4),
-                    TEXT_STYLE.clone(),
+                let mut best = TextBuffer::<24>::new();
+                if !mode.label().is_empty() {
+                    let _ = write!(best, "{} ", mode.label());
+                }
+                if self.new_best {
+                    let _ = write!(best, "NEW BEST");
+                } else {
+                    let _ = write!(best, "BEST {}", self.high_scores.best(mode));
+                }
+                let best_text = Text::with_baseline(
+                    best.as_str(),
+                    Point::new(64, 11),
+                    TEXT_STYLE,
                     embedded_graphics::text::Baseline::Top,
                 );
-                draw_text(display, score_text);
+                draw_text(display, best_text);
                 let game_over_text = Text::with_baseline(
                     "GAME OVER, TRY AGAIN?",
                     Point::new(36, 32),
@@ -326,10 +800,119 @@ impl Game {
                 );
                 draw_text(display, game_over_text);
             }
+            // Player two's HUD on top, player one's at the bottom
+            Scene::Playing(Run::Versus(scores, hps)) => {
+                self.draw_hud(display, Player::Two, scores[1], Some(hps[1]), 4);
+                self.draw_hud(display, Player::One, scores[0], Some(hps[0]), 56);
+            }
+            Scene::GameOver(run @ Run::Versus(scores, _)) => {
+                let mut buffer = TextBuffer::<24>::new();
+                let _ = write!(buffer, "{} - {}", scores[0], scores[1]);
+                let score_text = Text::with_baseline(
+                    buffer.as_str(),
+                    Point::new(64, 4),
+                    TEXT_STYLE,
+                    embedded_graphics::text::Baseline::Top,
+                );
+                draw_text(display, score_text);
+                let winner_text = Text::with_baseline(
+                    match run.winner() {
+                        Some(Player::Two) => "P2 WINS, TRY AGAIN?",
+                        _ => "P1 WINS, TRY AGAIN?",
+                    },
+                    Point::new(36, 32),
+                    TEXT_STYLE,
+                    embedded_graphics::text::Baseline::Top,
+                );
+                draw_text(display, winner_text);
+            }
+            _ => {}
+        }
+        if !scene.is_playing() {
+            for (x, selection) in SELECTORS {
+                let label = Text::with_text_style(
+                    selection.label(),
+                    Point::new(x + selection.width() / 2, SELECTOR_Y - 1),
+                    TEXT_STYLE,
+                    TextStyleBuilder::new()
+                        .alignment(Alignment::Center)
+                        .baseline(Baseline::Bottom)
+                        .build(),
+                );
+                draw_text(display, label);
+            }
         }
         for object in self.object_handler.iter() {
             object.draw(display);
         }
-        self.pudge.draw(display);
+        for &player in self.players() {
+            self.pudges[player.index()].draw(display);
+        }
+        self.floating_texts.draw(display);
+        let object_boxes = self.object_handler.iter().map(GameObject::collision_box);
+        let pudge_boxes = self.players().iter().flat_map(|player| {
+            let pudge = &self.pudges[player.index()];
+            [Some(pudge.body_box()), pudge.active_hook_box()]
+        });
+        self.debug_overlay
+            .draw_hitboxes(display, object_boxes.chain(pudge_boxes.flatten()));
+    }
+
+    // Score and combo multiplier on the left, HP or the time left on the right,
+    // `y` is the top of the score
+    fn draw_hud(
+        &self,
+        display: &mut DisplayEnum,
+        player: Player,
+        score: Score,
+        hp: Option<Hp>,
+        y: i32,
+    ) {
+        let mut buffer = itoa::Buffer::new();
+        let score_str = buffer.format(score);
+        let score_text = Text::with_baseline(
+            score_str,
+            Point::new(2, y),
+            TEXT_STYLE.clone(),
+            embedded_graphics::text::Baseline::Top,
+        );
+        draw_text(display, score_text);
+        let multiplier = self.combos[player.index()].multiplier();
+        if multiplier > 1 {
+            let mut buffer = TextBuffer::<4>::new();
+            let _ = write!(buffer, "x{}", multiplier);
+            let multiplier_text = Text::with_baseline(
+                buffer.as_str(),
+                Point::new(2 + ((score_str.len() + 1) * CHAR_WIDTH) as i32, y),
+                TEXT_STYLE,
+                embedded_graphics::text::Baseline::Top,
+            );
+            draw_text(display, multiplier_text);
+        }
+        match hp {
+            Some(hp) => draw_hp(display, hp, y + 2),
+            None => self.draw_countdown(display, y),
+        }
+    }
+
+    fn draw_countdown(&self, display: &mut DisplayEnum, y: i32) {
+        let (Some(ends_at), Some(now)) = (self.run_ends_at, self.time) else {
+            return;
+        };
+        let left = ends_at
+            .checked_duration_since(now)
+            .map_or(0, |left| left.to_millis().div_ceil(1000));
+        let mut buffer = TextBuffer::<8>::new();
+        let _ = write!(buffer, "{}S", left);
+        let countdown_text = Text::with_text_style(
+            buffer.as_str(),
+            Point::new(SCREEN_WIDTH as i32 - 4, y),
+            TEXT_STYLE,
+            TextStyleBuilder::new()
+                .alignment(Alignment::Right)
+                .baseline(Baseline::Top)
+                .build(),
+        );
+        draw_text(display, countdown_text);
     }
 }
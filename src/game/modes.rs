@@ -0,0 +1,188 @@
+use fugit::MicrosDurationU64;
+
+use super::{Hp, Score};
+
+// Single player rules, picked on the title and game over screens
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    // Until HP runs out
+    Classic,
+    // No HP, the run ends when the clock does
+    TimeAttack,
+    // One HP and a miss costs it too
+    SuddenDeath,
+}
+
+pub const MODES: [Mode; 3] = [Mode::Classic, Mode::TimeAttack, Mode::SuddenDeath];
+
+impl Mode {
+    // `None` for a run without HP
+    pub fn start_hp(&self) -> Option<Hp> {
+        match self {
+            Mode::Classic => Some(3),
+            Mode::TimeAttack => None,
+            Mode::SuddenDeath => Some(1),
+        }
+    }
+
+    pub fn time_limit(&self) -> Option<MicrosDurationU64> {
+        match self {
+            Mode::TimeAttack => Some(MicrosDurationU64::secs(60)),
+            _ => None,
+        }
+    }
+
+    pub fn misses_cost_hp(&self) -> bool {
+        *self == Mode::SuddenDeath
+    }
+
+    // Over its selector and next to its high score
+    pub fn label(&self) -> &'static str {
+        match self {
+            Mode::Classic => "",
+            Mode::TimeAttack => "60S",
+            Mode::SuddenDeath => "1HP",
+        }
+    }
+
+    // On the high scores scene
+    pub fn title(&self) -> &'static str {
+        match self {
+            Mode::Classic => "CLASSIC",
+            Mode::TimeAttack => "TIME ATTACK",
+            Mode::SuddenDeath => "SUDDEN DEATH",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Mode::Classic => "classic",
+            Mode::TimeAttack => "time-attack",
+            Mode::SuddenDeath => "sudden-death",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Mode> {
+        MODES.into_iter().find(|mode| mode.name() == name)
+    }
+
+    fn index(&self) -> usize {
+        match self {
+            Mode::Classic => 0,
+            Mode::TimeAttack => 1,
+            Mode::SuddenDeath => 2,
+        }
+    }
+}
+
+// Best score of each mode since power on
+#[derive(Debug, Default)]
+pub struct HighScores {
+    best: [Score; MODES.len()],
+}
+
+impl HighScores {
+    pub fn best(&self, mode: Mode) -> Score {
+        self.best[mode.index()]
+    }
+
+    // True for a new best
+    pub fn record(&mut self, mode: Mode, score: Score) -> bool {
+        let best = &mut self.best[mode.index()];
+        if score <= *best {
+            return false;
+        }
+        *best = score;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controls::{ControlEnum, Player};
+    use crate::game::testing::{game_in_run, tick_clock};
+    use crate::game::{Game, GameEvent, Run, Scene, TICK};
+
+    // Ticks with `control` held until the run is over, returns the ticks and events
+    fn play_out(game: &mut Game, control: ControlEnum) -> (u64, Vec<GameEvent>) {
+        let mut events = Vec::new();
+        let mut tick = 0;
+        while game.scene().is_playing() {
+            assert!(tick < 100_000, "run never ended");
+            game.control(Player::One, control);
+            game.process(tick_clock(tick)).unwrap();
+            events.extend(game.drain_events());
+            tick += 1;
+        }
+        (tick, events)
+    }
+
+    #[test]
+    fn test_high_scores_are_per_mode() {
+        let mut high_scores = HighScores::default();
+        assert!(high_scores.record(Mode::Classic, 500));
+        assert!(!high_scores.record(Mode::Classic, 300));
+        assert!(high_scores.record(Mode::TimeAttack, 200));
+        assert!(!high_scores.record(Mode::SuddenDeath, 0));
+
+        assert_eq!(high_scores.best(Mode::Classic), 500);
+        assert_eq!(high_scores.best(Mode::TimeAttack), 200);
+        assert_eq!(high_scores.best(Mode::SuddenDeath), 0);
+        assert_eq!(Mode::from_name("sudden-death"), Some(Mode::SuddenDeath));
+    }
+
+    #[test]
+    fn test_time_attack_ends_with_the_clock() {
+        let mut game = game_in_run(Mode::TimeAttack);
+        assert_eq!(game.scene().run().and_then(|run| run.hp(Player::One)), None);
+
+        // Hooking at nothing only misses, which costs no HP here
+        let (ticks, events) = play_out(&mut game, ControlEnum::Hook);
+        assert_eq!(ticks * TICK.to_micros() / 1_000_000, 60);
+        assert!(events
+            .iter()
+            .any(|event| matches!(event, GameEvent::Missed(_))));
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event, GameEvent::Damaged(_, _))));
+        assert!(matches!(
+            game.scene(),
+            Scene::GameOver(Run::Solo(Mode::TimeAttack, _, _, 1))
+        ));
+    }
+
+    #[test]
+    fn test_sudden_death_ends_on_the_first_miss() {
+        let mut game = game_in_run(Mode::SuddenDeath);
+
+        // Nothing spawns during the stage banner, the first hook misses
+        let (_, events) = play_out(&mut game, ControlEnum::Hook);
+        let misses = events
+            .iter()
+            .filter(|event| matches!(event, GameEvent::Missed(_)))
+            .count();
+        assert_eq!(misses, 1);
+        assert!(events.contains(&GameEvent::Damaged(Player::One, 0)));
+        assert!(matches!(
+            game.scene(),
+            Scene::GameOver(Run::Solo(Mode::SuddenDeath, _, _, 1))
+        ));
+    }
+
+    #[test]
+    fn test_finished_runs_set_their_mode_high_score() {
+        let mut game = game_in_run(Mode::SuddenDeath);
+        game.add_score(Player::One, 500).unwrap();
+        game.end_run();
+        assert_eq!(game.high_score(Mode::SuddenDeath), 500);
+        assert_eq!(game.high_score(Mode::Classic), 0);
+        assert!(game.new_best);
+
+        game.start_run(Mode::SuddenDeath);
+        game.add_score(Player::One, 300).unwrap();
+        game.end_run();
+        assert_eq!(game.high_score(Mode::SuddenDeath), 500);
+        assert!(!game.new_best);
+    }
+}
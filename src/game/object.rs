@@ -1,83 +1,121 @@
-#[cfg(not(target_os = "none"))]
-use std::collections::{
-    btree_map::{Values, ValuesMut},
-    BTreeMap,
-};
-
-#[cfg(target_os = "none")]
-extern crate alloc;
-#[cfg(target_os = "none")]
-use alloc::collections::{
-    btree_map::{Values, ValuesMut},
-    BTreeMap,
-};
+use core::fmt;
 
 use embedded_graphics::image::Image;
+use heapless::Vec;
 use nalgebra::Vector2;
 
 use crate::graphics::{
-    display::{DisplayEnum, DISPLAY_HEIGHT},
+    display::DisplayEnum,
     image::draw_image,
-    resources::{Sprite, CREEP_HEIGHT, CREEP_WIDTH, SCREEN_WIDTH},
+    resources::{Sprite, SCREEN_WIDTH},
 };
 
-use super::{collisions::CollisionRectangle, Creep, GamePoint, Hookable, Id, Speed};
+use super::{
+    animation::Animation,
+    collisions::{CollisionMask, CollisionRectangle},
+    Creep, GamePoint, Hookable, Speed, TickCount,
+};
+
+const WALK_FRAME_TICKS: TickCount = 8;
+// Most objects alive at once, spawning waits for a free slot past this
+pub const OBJECT_CAPACITY: usize = 16;
+
+// Slot index plus the slot's generation, so an id held after its object was
+// removed doesn't resolve to whatever got the slot next
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Id {
+    index: u16,
+    generation: u16,
+}
+
+impl Id {
+    // Objects get their real id once they're inserted into the handler
+    const UNASSIGNED: Id = Id {
+        index: u16::MAX,
+        generation: 0,
+    };
+}
+
+impl fmt::Display for Id {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}v{}", self.index, self.generation)
+    }
+}
 
 #[derive(Debug)]
 pub struct GameObject {
     pub id: Id,
     location: GamePoint,
     pub game_type: Hookable,
-    sprite_type: Sprite,
+    animation: Animation,
     width: u8,
     height: u8,
     reward: u16,
     speed: Speed,
+    // Narrow phase against the sprite's lit pixels instead of its whole box
+    pixel_collision: bool,
+}
+
+#[derive(Debug)]
+pub struct Collision<'a> {
+    pub object: &'a GameObject,
+    // Fraction of the tick at which the object gets hit
+    pub toi: f32,
+    // From the hook tip to the object at the start of the tick
+    pub distance: f32,
+}
+
+struct Slot {
+    generation: u16,
+    object: Option<GameObject>,
 }
 
 pub struct ObjectHandler {
-    objects: BTreeMap<Id, GameObject>,
+    slots: [Slot; OBJECT_CAPACITY],
+    len: usize,
 }
 
 impl GameObject {
-    pub fn make_creep(id: Id, location: GamePoint, alliegiance: Creep, speed: Speed) -> Self {
+    pub fn make_creep(location: GamePoint, alliegiance: Creep, speed: Speed) -> Self {
+        let sprite = Sprite::Creep(alliegiance);
+        let (width, height) = sprite.size();
         GameObject {
-            id,
+            id: Id::UNASSIGNED,
             location,
             game_type: Hookable::Creep(alliegiance),
             reward: 100,
             speed,
-            sprite_type: Sprite::Creep(alliegiance),
-            width: CREEP_WIDTH,
-            height: CREEP_HEIGHT,
+            animation: Animation::new(sprite, WALK_FRAME_TICKS),
+            width,
+            height,
+            pixel_collision: true,
         }
     }
 
-    pub fn calculate_score(&self) -> u32 {
-        if self.game_type != Hookable::Creep(Creep::Radiant) {
-            return 0;
-        }
-        self.reward as u32 + (self.speed + (DISPLAY_HEIGHT as f32 - self.location.y)) as u32
+    // Drains by one every tick down to 1, see `scoring`
+    pub fn reward(&self) -> u16 {
+        self.reward
     }
 }
 
 pub enum GameObjectSignal {
-    OutOfBounds(Id),
+    OutOfBounds,
 }
 
 impl GameObject {
     pub fn tick(&mut self) -> Option<GameObjectSignal> {
         self.location.x += self.speed;
+        self.animation.tick();
         if self.reward > 1 {
             self.reward -= 1;
         }
         if self.location.x < 0.0 || self.location.x > SCREEN_WIDTH as f32 {
-            return Some(GameObjectSignal::OutOfBounds(self.id));
+            return Some(GameObjectSignal::OutOfBounds);
         }
         None
     }
     pub fn draw(&self, display: &mut DisplayEnum) {
-        let ref img_raw = self.sprite_type.get_image();
+        let ref img_raw = self.animation.image();
         let img = Image::new(img_raw, self.location.into());
         draw_image(display, img);
     }
@@ -86,45 +124,204 @@ impl GameObject {
         return point.in_rect(&self.location, &self.width, &self.height);
     }
 
-    pub fn intersects(&self, other_rect: &CollisionRectangle) -> bool {
-        let collision_rect = CollisionRectangle::new(
+    pub fn location(&self) -> GamePoint {
+        self.location
+    }
+
+    pub fn collision_box(&self) -> CollisionRectangle {
+        CollisionRectangle::new(
             self.location.into(),
             Vector2::new(self.width.into(), self.height.into()),
-        );
-        collision_rect.intersects(other_rect)
+        )
+    }
+
+    // Uses the frame that's currently drawn
+    pub fn collision_mask(&self) -> Option<CollisionMask> {
+        if !self.pixel_collision {
+            return None;
+        }
+        Some(CollisionMask::new(self.animation.image()))
+    }
+
+    pub fn velocity(&self) -> Vector2<f32> {
+        Vector2::new(self.speed, 0.0)
+    }
+
+    // Time of impact with `other_rect` moving by `velocity` during this tick
+    pub fn sweep(&self, other_rect: &CollisionRectangle, velocity: Vector2<f32>) -> Option<f32> {
+        other_rect.sweep_masked(
+            None,
+            velocity,
+            &self.collision_box(),
+            self.collision_mask().as_ref(),
+            self.velocity(),
+        )
     }
 }
 
 impl ObjectHandler {
     pub fn new() -> Self {
         ObjectHandler {
-            objects: BTreeMap::new(),
+            slots: core::array::from_fn(|_| Slot {
+                generation: 0,
+                object: None,
+            }),
+            len: 0,
         }
     }
 
-    pub fn insert(&mut self, obj: GameObject) -> Option<GameObject> {
-        self.objects.insert(obj.id, obj)
+    // Hands the object back when every slot is taken
+    pub fn insert(&mut self, mut obj: GameObject) -> Result<Id, GameObject> {
+        let Some(index) = self.slots.iter().position(|slot| slot.object.is_none()) else {
+            return Err(obj);
+        };
+        let slot = &mut self.slots[index];
+        let id = Id {
+            index: index as u16,
+            generation: slot.generation,
+        };
+        obj.id = id;
+        slot.object = Some(obj);
+        self.len += 1;
+        Ok(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
     }
 
     pub fn remove(&mut self, id: Id) -> Option<GameObject> {
-        self.objects.remove(&id)
+        let slot = self.slots.get_mut(id.index as usize)?;
+        if slot.generation != id.generation {
+            return None;
+        }
+        let object = slot.object.take()?;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.len -= 1;
+        Some(object)
     }
 
-    pub fn iter_mut(&mut self) -> ValuesMut<Id, GameObject> {
-        self.objects.values_mut()
+    // Keeps the objects `keep` returns true for and hands back the rest.
+    // There's room for every slot so nothing is left behind in one pass.
+    pub fn retain_mut<F>(&mut self, mut keep: F) -> Vec<GameObject, OBJECT_CAPACITY>
+    where
+        F: FnMut(&mut GameObject) -> bool,
+    {
+        let mut removed = Vec::new();
+        for slot in self.slots.iter_mut() {
+            let Some(object) = slot.object.as_mut() else {
+                continue;
+            };
+            if keep(object) {
+                continue;
+            }
+            if let Some(object) = slot.object.take() {
+                slot.generation = slot.generation.wrapping_add(1);
+                self.len -= 1;
+                // Can't overflow, `removed` holds as many objects as there are slots
+                let _ = removed.push(object);
+            }
+        }
+        removed
     }
 
-    pub fn iter(&self) -> Values<u16, GameObject> {
-        self.objects.values()
+    // Ids handed out before stay stale
+    pub fn clear(&mut self) {
+        self.retain_mut(|_| false);
     }
 
-    // Returns first collision
-    pub fn get_collision(&self, rect: &CollisionRectangle) -> Option<&GameObject> {
-        for obj in self.iter() {
-            if obj.intersects(rect) {
-                return Some(obj);
-            }
+    pub fn iter(&self) -> impl Iterator<Item = &GameObject> {
+        self.slots.iter().filter_map(|slot| slot.object.as_ref())
+    }
+
+    // Every object hit by `rect` moving by `velocity` this tick, nearest to
+    // `tip` first. Objects are swept by their own speed as well.
+    pub fn get_swept_collisions(
+        &self,
+        rect: &CollisionRectangle,
+        velocity: Vector2<f32>,
+        tip: Vector2<f32>,
+    ) -> Vec<Collision<'_>, OBJECT_CAPACITY> {
+        let mut collisions: Vec<Collision, OBJECT_CAPACITY> = self
+            .iter()
+            .filter_map(|object| {
+                let toi = object.sweep(rect, velocity)?;
+                let distance = object.collision_box().distance_to(tip);
+                Some(Collision {
+                    object,
+                    toi,
+                    distance,
+                })
+            })
+            .collect();
+        collisions.sort_unstable_by(|a, b| {
+            a.distance
+                .total_cmp(&b.distance)
+                .then(a.toi.total_cmp(&b.toi))
+        });
+        collisions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn creep_at(x: i32, y: i32) -> GameObject {
+        GameObject::make_creep(GamePoint::new(x, y), Creep::Radiant, 0.0)
+    }
+
+    #[test]
+    fn test_swept_collisions_nearest_first() {
+        let mut handler = ObjectHandler::new();
+        // First slot further away so insertion order would pick the wrong one
+        let far = handler.insert(creep_at(0, 10)).unwrap();
+        let near = handler.insert(creep_at(2, 28)).unwrap();
+        handler.insert(creep_at(60, 28)).unwrap();
+
+        let hook = CollisionRectangle::new(Vector2::new(4.0, 50.0), Vector2::new(5.0, 3.0));
+        let tip = Vector2::new(6.0, 50.0);
+        let collisions = handler.get_swept_collisions(&hook, Vector2::new(0.0, -50.0), tip);
+
+        let ids: Vec<Id, OBJECT_CAPACITY> = collisions.iter().map(|hit| hit.object.id).collect();
+        assert_eq!(ids, [near, far]);
+        assert!(collisions[0].distance < collisions[1].distance);
+        assert!(collisions[0].toi < collisions[1].toi);
+    }
+
+    #[test]
+    fn test_insert_fails_when_full() {
+        let mut handler = ObjectHandler::new();
+        for _ in 0..OBJECT_CAPACITY {
+            assert!(handler.insert(creep_at(0, 28)).is_ok());
         }
-        None
+        assert!(handler.insert(creep_at(0, 28)).is_err());
+        assert_eq!(handler.len(), OBJECT_CAPACITY);
+    }
+
+    #[test]
+    fn test_stale_id_after_slot_reuse() {
+        let mut handler = ObjectHandler::new();
+        let old = handler.insert(creep_at(0, 28)).unwrap();
+        assert!(handler.remove(old).is_some());
+
+        let new = handler.insert(creep_at(10, 28)).unwrap();
+        assert_ne!(old, new);
+        assert!(handler.remove(old).is_none());
+        assert_eq!(handler.remove(new).map(|obj| obj.id), Some(new));
+    }
+
+    #[test]
+    fn test_retain_returns_every_despawned_object() {
+        let mut handler = ObjectHandler::new();
+        for x in 0..OBJECT_CAPACITY as i32 {
+            handler.insert(creep_at(x, 28)).unwrap();
+        }
+
+        let removed = handler.retain_mut(|obj| obj.location.x >= 4.0);
+        assert_eq!(removed.len(), 4);
+        assert_eq!(handler.len(), OBJECT_CAPACITY - 4);
+        assert!(removed.iter().all(|obj| obj.location.x < 4.0));
+        assert!(handler.insert(creep_at(0, 28)).is_ok());
     }
 }
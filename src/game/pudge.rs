@@ -1,25 +1,51 @@
-#[cfg(target_os = "none")]
-extern crate alloc;
-
 use crate::{
     controls::ControlEnum,
     graphics::{
-        self,
         display::DisplayEnum,
-        image::draw_image,
-        resources::{HOOK_WIDTH, PUDGE_HEIGHT, PUDGE_WIDTH, SCREEN_WIDTH},
+        image::{draw_chain, draw_image, draw_image_flipped},
+        resources::{
+            Sprite, HOOK_HEIGHT, HOOK_WIDTH, PUDGE_HEIGHT, PUDGE_WIDTH, SCREEN_HEIGHT, SCREEN_WIDTH,
+        },
     },
-    SpriteImage,
 };
 
 use super::{
+    animation::Animation,
     collisions::CollisionRectangle,
     object::{GameObject, ObjectHandler},
-    GamePoint, Hookable, Speed, TickCount,
+    Creep, GamePoint, Hookable, Speed, TickCount,
 };
 use embedded_graphics::{geometry::Point, image::Image};
 use nalgebra::Vector2;
 
+const POSE_FRAME_TICKS: TickCount = 6;
+const HOOK_FRAME_TICKS: TickCount = 4;
+
+// Which edge of the screen Pudge walks along, he throws towards the other one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Side {
+    Bottom,
+    Top,
+}
+
+impl Side {
+    // Creeps this side scores from, and loses HP to when they get away
+    pub fn creep(&self) -> Creep {
+        match self {
+            Side::Bottom => Creep::Radiant,
+            Side::Top => Creep::Dire,
+        }
+    }
+
+    // Sign of the hook's y velocity while it flies
+    fn throw_direction(&self) -> f32 {
+        match self {
+            Side::Bottom => -1.0,
+            Side::Top => 1.0,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq)]
 enum HookState {
     Flying,
@@ -41,18 +67,32 @@ pub enum PudgeSignal {
 }
 
 pub struct Pudge {
+    side: Side,
     location: GamePoint,
     speed: Speed,
     hook_speed: Speed,
     hook_boost: Speed,
     movement_boost: Speed,
     state: PudgeState,
+    body_animation: Animation,
+    hook_animation: Animation,
 }
 
 impl Default for Pudge {
     fn default() -> Self {
+        Pudge::new(Side::Bottom)
+    }
+}
+
+impl Pudge {
+    pub fn new(side: Side) -> Self {
+        let location = match side {
+            Side::Bottom => GamePoint::new(64, 64 - 13),
+            Side::Top => GamePoint::new(64, 0),
+        };
         return Pudge {
-            location: GamePoint::new(64, 64 - 13),
+            side,
+            location,
             #[cfg(target_arch = "wasm32")]
             speed: 4.0,
             #[cfg(not(target_arch = "wasm32"))]
@@ -61,11 +101,45 @@ impl Default for Pudge {
             hook_boost: 0.1,
             movement_boost: 0.15,
             state: PudgeState::Walking,
+            body_animation: Animation::new(Sprite::Pudge, POSE_FRAME_TICKS),
+            hook_animation: Animation::new(Sprite::Hook, HOOK_FRAME_TICKS),
         };
     }
-}
 
-impl Pudge {
+    pub fn side(&self) -> Side {
+        self.side
+    }
+
+    // Distance walked per control
+    pub fn walk_speed(&self) -> Speed {
+        self.speed
+    }
+
+    // Only a walking Pudge can move or throw
+    pub fn is_walking(&self) -> bool {
+        self.state == PudgeState::Walking
+    }
+
+    // How far Pudge has to walk for a hook thrown from there to meet
+    // `object` head on, with the object keeping its speed. None when the
+    // hook can't reach it any more or the spot is off screen.
+    pub fn aim_offset(&self, object: &GameObject) -> Option<f32> {
+        let hook = Pudge::hook_box(self.side, &self.location);
+        let target = object.collision_box();
+        let gap = match self.side {
+            Side::Bottom => hook.top() - target.bottom(),
+            Side::Top => target.top() - hook.bottom(),
+        };
+        if gap < 0.0 {
+            return None;
+        }
+        let flight_ticks = gap / self.hook_speed;
+        let meet_x = target.center().x + object.velocity().x * flight_ticks;
+        let offset = meet_x - hook.center().x;
+        let spot = self.location.x + offset;
+        (spot >= 0.0 && spot <= (SCREEN_WIDTH - PUDGE_WIDTH) as f32).then_some(offset)
+    }
+
     pub fn act(&mut self, controls: Option<ControlEnum>) {
         if self.state == PudgeState::Walking {
             if let Some(control) = controls {
@@ -75,38 +149,56 @@ impl Pudge {
     }
 
     pub fn tick(&mut self, object_handler: &mut ObjectHandler) -> Option<PudgeSignal> {
+        self.body_animation.tick();
+        self.hook_animation.tick();
         if let PudgeState::Hooking(hook_location, hook_state) = &mut self.state {
             match hook_state {
                 HookState::Flying => {
                     let mut new_location = hook_location.clone();
+                    let hook_box = Pudge::hook_box(self.side, hook_location);
+                    let direction = self.side.throw_direction();
+                    let hook_velocity = Vector2::new(0.0, direction * self.hook_speed);
 
-                    new_location.y -= self.hook_speed;
-
-                    if new_location.y <= 0.0 {
-                        self.set_state(PudgeState::Hooking(new_location, HookState::Reeling(None)));
-                        return Some(PudgeSignal::Missed);
-                    }
-                    let hook_box_location = Vector2::new(new_location.x, new_location.y + 5.0);
-                    let hook_box = CollisionRectangle::new(
-                        hook_box_location,
-                        Vector2::new(HOOK_WIDTH.into(), 3.0),
-                    );
-
-                    if let Some(collision) = object_handler.get_collision(&hook_box) {
-                        let obj = object_handler.remove(collision.id).unwrap();
+                    // Swept so a fast hook can't skip over a creep between ticks
+                    let nearest = object_handler
+                        .get_swept_collisions(
+                            &hook_box,
+                            hook_velocity,
+                            Pudge::hook_tip(self.side, hook_location),
+                        )
+                        .first()
+                        .map(|collision| (collision.object.id, collision.toi));
+                    let hooked =
+                        nearest.and_then(|(id, toi)| Some((object_handler.remove(id)?, toi)));
+                    if let Some((obj, toi)) = hooked {
+                        new_location.y += direction * self.hook_speed * toi;
                         self.set_state(PudgeState::Hooking(
                             new_location,
                             HookState::Reeling(Some(obj.game_type)),
                         ));
                         return Some(PudgeSignal::Hooked(obj));
                     }
+
+                    new_location.y += direction * self.hook_speed;
+                    let missed = match self.side {
+                        Side::Bottom => new_location.y <= 0.0,
+                        Side::Top => new_location.y + HOOK_HEIGHT as f32 >= SCREEN_HEIGHT as f32,
+                    };
+                    if missed {
+                        self.set_state(PudgeState::Hooking(new_location, HookState::Reeling(None)));
+                        return Some(PudgeSignal::Missed);
+                    }
                     self.set_state(PudgeState::Hooking(new_location, HookState::Flying))
                 }
                 HookState::Reeling(obj) => {
                     let mut new_location = hook_location.clone();
                     let obj_clone = obj.clone();
-                    new_location.y += self.hook_speed * 1.2;
-                    if new_location.y >= self.location.y - PUDGE_HEIGHT as f32 {
+                    new_location.y -= self.side.throw_direction() * self.hook_speed * 1.2;
+                    let reeled_in = match self.side {
+                        Side::Bottom => new_location.y >= self.location.y - PUDGE_HEIGHT as f32,
+                        Side::Top => new_location.y <= self.location.y + PUDGE_HEIGHT as f32,
+                    };
+                    if reeled_in {
                         self.set_state(PudgeState::Walking);
                         self.hook_speed += self.hook_boost;
                         self.speed += self.movement_boost;
@@ -121,13 +213,65 @@ impl Pudge {
         }
         None
     }
+    // Only the hook's point can catch things, not the whole sprite
+    fn hook_box(side: Side, hook_location: &GamePoint) -> CollisionRectangle {
+        let offset = match side {
+            Side::Bottom => 5.0,
+            Side::Top => HOOK_HEIGHT as f32 - 8.0,
+        };
+        let hook_box_location = Vector2::new(hook_location.x, hook_location.y + offset);
+        CollisionRectangle::new(hook_box_location, Vector2::new(HOOK_WIDTH.into(), 3.0))
+    }
+
+    fn hook_tip(side: Side, hook_location: &GamePoint) -> Vector2<f32> {
+        let y = match side {
+            Side::Bottom => hook_location.y,
+            Side::Top => hook_location.y + HOOK_HEIGHT as f32,
+        };
+        Vector2::new(hook_location.x + HOOK_WIDTH as f32 / 2.0, y)
+    }
+
+    pub fn body_box(&self) -> CollisionRectangle {
+        CollisionRectangle::new(
+            self.location.into(),
+            Vector2::new(PUDGE_WIDTH.into(), PUDGE_HEIGHT.into()),
+        )
+    }
+
+    // Hook hitbox while it's still able to catch something
+    pub fn active_hook_box(&self) -> Option<CollisionRectangle> {
+        if let PudgeState::Hooking(hook_location, HookState::Flying) = &self.state {
+            return Some(Pudge::hook_box(self.side, hook_location));
+        }
+        None
+    }
+
     fn set_state(&mut self, state: PudgeState) {
         self.state = state;
+        self.update_animations();
+    }
+
+    fn update_animations(&mut self) {
+        match &self.state {
+            PudgeState::Walking | PudgeState::Cooldown(_) => {
+                self.body_animation.play(Sprite::Pudge)
+            }
+            PudgeState::Hooking(_, HookState::Flying) => {
+                self.body_animation.play(Sprite::PudgeThrowing);
+                self.hook_animation.play(Sprite::Hook);
+            }
+            PudgeState::Hooking(_, HookState::Reeling(_)) => {
+                self.body_animation.play(Sprite::PudgeReeling);
+                self.hook_animation.play(Sprite::HookReeling);
+            }
+        }
     }
 
     fn move_character(&mut self, controls: ControlEnum) {
         match controls {
-            ControlEnum::None => return,
+            ControlEnum::None | ControlEnum::Debug | ControlEnum::Hitboxes | ControlEnum::Pause => {
+                return
+            }
             ControlEnum::Left => {
                 let distance = self.speed;
                 if distance > self.location.x {
@@ -147,7 +291,10 @@ impl Pudge {
             ControlEnum::Hook => {
                 let mut hook_location = self.location.clone();
                 hook_location.y += PUDGE_HEIGHT as f32;
-                self.state = PudgeState::Hooking(self.location.clone(), HookState::Flying)
+                self.set_state(PudgeState::Hooking(
+                    self.location.clone(),
+                    HookState::Flying,
+                ))
             }
         }
     }
@@ -155,24 +302,98 @@ impl Pudge {
     pub fn draw(&self, display: &mut DisplayEnum) {
         self.draw_pudge(display);
         if let PudgeState::Hooking(position, hook_state) = &self.state {
-            self.draw_hook(display, position, hook_state)
+            self.draw_chain(display, position);
+            if let HookState::Reeling(Some(hooked)) = hook_state {
+                self.draw_hooked(display, position, hooked);
+            }
+            self.draw_hook(display, position)
+        }
+    }
+
+    fn draw_hook(&self, display: &mut DisplayEnum, position: &GamePoint) {
+        let hook_pos: Point = (*position).into();
+        let hook_raw = self.hook_animation.image();
+        match self.side {
+            Side::Bottom => draw_image(display, Image::new(&hook_raw, hook_pos)),
+            Side::Top => draw_image_flipped(display, &hook_raw, hook_pos),
         }
     }
 
-    fn draw_hook(&self, display: &mut DisplayEnum, position: &GamePoint, hook_state: &HookState) {
+    // From the hook's shaft back to Pudge's hand
+    fn draw_chain(&self, display: &mut DisplayEnum, position: &GamePoint) {
         let hook_pos: Point = (*position).into();
-        let hook_img = Pudge::get_hook_img(&hook_pos, hook_state);
-        draw_image(display, hook_img);
+        let pudge_pos: Point = self.location.into();
+        match self.side {
+            Side::Bottom => {
+                let hook_end = hook_pos + Point::new(HOOK_WIDTH as i32 / 2, HOOK_HEIGHT as i32);
+                let hand = pudge_pos + Point::new(HOOK_WIDTH as i32 / 2, 0);
+                if hook_end.y < hand.y {
+                    draw_chain(display, hook_end, hand);
+                }
+            }
+            Side::Top => {
+                let hook_end = hook_pos + Point::new(HOOK_WIDTH as i32 / 2, 0);
+                let hand = pudge_pos + Point::new(HOOK_WIDTH as i32 / 2, PUDGE_HEIGHT as i32);
+                if hook_end.y > hand.y {
+                    draw_chain(display, hand, hook_end);
+                }
+            }
+        }
     }
 
-    fn get_hook_img(position: &Point, hook_state: &HookState) -> SpriteImage {
-        match hook_state {
-            _ => Image::new(&graphics::resources::PUDGE_HOOK, *position),
+    // Hooked object hangs off the hook head while it's being dragged back
+    fn draw_hooked(&self, display: &mut DisplayEnum, position: &GamePoint, hooked: &Hookable) {
+        if let Some(sprite) = Sprite::for_hookable(hooked) {
+            let hook_pos: Point = (*position).into();
+            let (width, height) = sprite.size();
+            let offset_y = match self.side {
+                Side::Bottom => 2 - height as i32,
+                Side::Top => HOOK_HEIGHT as i32 - 2,
+            };
+            let offset = Point::new(HOOK_WIDTH as i32 / 2 - width as i32 / 2, offset_y);
+            let hooked_raw = sprite.get_frame(0);
+            match self.side {
+                Side::Bottom => draw_image(display, Image::new(&hooked_raw, hook_pos + offset)),
+                Side::Top => draw_image_flipped(display, &hooked_raw, hook_pos + offset),
+            }
         }
     }
 
     fn draw_pudge(&self, display: &mut DisplayEnum) {
-        let image = Image::new(&graphics::resources::PUDGE_BODY, self.location.into());
-        draw_image(display, image);
+        let body_raw = self.body_animation.image();
+        match self.side {
+            Side::Bottom => draw_image(display, Image::new(&body_raw, self.location.into())),
+            Side::Top => draw_image_flipped(display, &body_raw, self.location.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_top_pudge_hooks_downwards() {
+        let mut objects = ObjectHandler::new();
+        objects
+            .insert(GameObject::make_creep(
+                GamePoint::new(62, 28),
+                Creep::Dire,
+                0.0,
+            ))
+            .unwrap();
+        let mut pudge = Pudge::new(Side::Top);
+        pudge.act(Some(ControlEnum::Hook));
+
+        let mut signals = (0..100).filter_map(|_| pudge.tick(&mut objects));
+        let Some(PudgeSignal::Hooked(hooked)) = signals.next() else {
+            panic!("hook never reached the lane");
+        };
+        assert_eq!(hooked.game_type, Hookable::Creep(Creep::Dire));
+        assert!(matches!(
+            signals.next(),
+            Some(PudgeSignal::Reeled(Some(Hookable::Creep(Creep::Dire))))
+        ));
+        assert_eq!(pudge.state, PudgeState::Walking);
     }
 }
@@ -0,0 +1,487 @@
+use core::fmt::Write;
+
+use embedded_graphics::image::Image;
+use embedded_graphics::text::{Alignment, Baseline, TextStyleBuilder};
+use embedded_graphics::{geometry::Point, text::Text};
+use heapless::Vec;
+
+use super::{Game, GameError, Hp, Mode, Score, StageNr, MODES};
+use crate::controls::{ControlEnum, Player};
+use crate::graphics::display::DisplayEnum;
+use crate::graphics::image::{draw_image, draw_text};
+use crate::graphics::resources::{SPLASH, TEXT_STYLE};
+use crate::graphics::utils::TextBuffer;
+
+// A single player run or a versus match, kept by the scene it's played in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Run {
+    // HP stays 0 in modes without it
+    Solo(Mode, Score, Hp, StageNr),
+    // Scores and HP indexed by `Player::index`
+    Versus([Score; 2], [Hp; 2]),
+}
+
+pub const VERSUS_START: Run = Run::Versus([0; 2], [3; 2]);
+
+impl Run {
+    pub fn solo(mode: Mode) -> Self {
+        Run::Solo(mode, 0, mode.start_hp().unwrap_or(0), 1)
+    }
+
+    pub fn mode(&self) -> Option<Mode> {
+        match self {
+            Run::Solo(mode, _, _, _) => Some(*mode),
+            Run::Versus(_, _) => None,
+        }
+    }
+
+    // `None` for player two outside versus and in modes without HP
+    pub fn hp(&self, player: Player) -> Option<Hp> {
+        match (self, player) {
+            (Run::Solo(mode, _, hp, _), Player::One) => mode.start_hp().map(|_| *hp),
+            (Run::Versus(_, hps), _) => Some(hps[player.index()]),
+            _ => None,
+        }
+    }
+
+    // Never drops below 0
+    pub fn add_score(&mut self, player: Player, points: Score) -> Result<(), GameError> {
+        let score = match (self, player) {
+            (Run::Solo(_, score, _, _), Player::One) => score,
+            (Run::Versus(scores, _), _) => &mut scores[player.index()],
+            (Run::Solo(_, _, _, _), Player::Two) => return Err(GameError::NotPlaying(player)),
+        };
+        *score = score.saturating_add(points).max(0);
+        Ok(())
+    }
+
+    // Does nothing in modes without HP
+    pub fn damage(&mut self, player: Player) -> Result<(), GameError> {
+        match (self, player) {
+            (Run::Solo(mode, _, hp, _), Player::One) => {
+                if mode.start_hp().is_some() {
+                    *hp = hp.saturating_sub(1);
+                }
+            }
+            (Run::Versus(_, hps), _) => {
+                let hp = &mut hps[player.index()];
+                *hp = hp.saturating_sub(1);
+            }
+            (Run::Solo(_, _, _, _), Player::Two) => return Err(GameError::NotPlaying(player)),
+        }
+        Ok(())
+    }
+
+    // Someone ran out of HP
+    pub fn is_over(&self) -> bool {
+        match self {
+            Run::Solo(mode, _, hp, _) => mode.start_hp().is_some() && *hp == 0,
+            Run::Versus(_, hps) => hps.contains(&0),
+        }
+    }
+
+    // Whoever has HP left, or the leader of a match that was ended early
+    pub fn winner(&self) -> Option<Player> {
+        match self {
+            Run::Solo(_, _, _, _) => None,
+            Run::Versus(_, hps) if hps[Player::One.index()] == 0 => Some(Player::Two),
+            Run::Versus(_, hps) if hps[Player::Two.index()] == 0 => Some(Player::One),
+            Run::Versus(scores, _) if scores[1] > scores[0] => Some(Player::Two),
+            Run::Versus(_, _) => Some(Player::One),
+        }
+    }
+
+    pub fn next_stage(&mut self) {
+        if let Run::Solo(_, _, _, stage) = self {
+            *stage += 1;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scene {
+    // Splash, the hook goes on to the menu, left to the high scores and
+    // right to the settings
+    Title,
+    // A creep to hook for each mode and versus
+    Menu,
+    Playing(Run),
+    // Over a single player run, which stands still until it's popped.
+    // Left restarts the run and right quits to the menu.
+    Paused,
+    // How the run went, with the menu's creeps to go again
+    GameOver(Run),
+    // Over the title, any input pops them
+    HighScores,
+    Settings,
+}
+
+impl Scene {
+    pub fn is_playing(&self) -> bool {
+        matches!(self, Scene::Playing(_))
+    }
+
+    // Of the run being played or just over
+    pub fn run(&self) -> Option<Run> {
+        match self {
+            Scene::Playing(run) | Scene::GameOver(run) => Some(*run),
+            _ => None,
+        }
+    }
+
+    pub fn to_str(&self) -> &str {
+        match self {
+            Scene::Title => "Title",
+            Scene::Menu => "Menu",
+            Scene::Playing(Run::Solo(_, _, _, _)) => "Game in progress",
+            Scene::Playing(Run::Versus(_, _)) => "Versus in progress",
+            Scene::Paused => "Paused",
+            Scene::GameOver(Run::Solo(_, _, _, _)) => "Game Over",
+            Scene::GameOver(Run::Versus(_, _)) => "Versus over",
+            Scene::HighScores => "High scores",
+            Scene::Settings => "Settings",
+        }
+    }
+}
+
+// Scenes pushed over the bottom one at most, the firmware has no heap
+const OVERLAY_DEPTH: usize = 3;
+
+pub(super) struct SceneStack {
+    bottom: Scene,
+    overlays: Vec<Scene, OVERLAY_DEPTH>,
+}
+
+impl SceneStack {
+    pub fn new(scene: Scene) -> Self {
+        SceneStack {
+            bottom: scene,
+            overlays: Vec::new(),
+        }
+    }
+
+    pub fn top(&self) -> Scene {
+        *self.overlays.last().unwrap_or(&self.bottom)
+    }
+
+    fn top_mut(&mut self) -> &mut Scene {
+        self.overlays.last_mut().unwrap_or(&mut self.bottom)
+    }
+
+    // What an overlay is drawn over
+    pub fn below_top(&self) -> Option<Scene> {
+        match self.overlays.len() {
+            0 => None,
+            1 => Some(self.bottom),
+            len => Some(self.overlays[len - 2]),
+        }
+    }
+
+    // The run on top, if it's being played
+    pub fn run_mut(&mut self) -> Option<&mut Run> {
+        match self.top_mut() {
+            Scene::Playing(run) => Some(run),
+            _ => None,
+        }
+    }
+
+    // False when full
+    fn push(&mut self, scene: Scene) -> bool {
+        self.overlays.push(scene).is_ok()
+    }
+
+    // The bottom scene stays
+    fn pop(&mut self) -> Option<Scene> {
+        self.overlays.pop()
+    }
+
+    fn replace(&mut self, scene: Scene) {
+        *self.top_mut() = scene;
+    }
+}
+
+impl Game {
+    pub fn scene(&self) -> Scene {
+        self.scenes.top()
+    }
+
+    // Replaces the scene on top, the old one releases what it put in the world
+    pub(super) fn switch_scene(&mut self, scene: Scene) {
+        let previous = self.scene();
+        self.leave(previous);
+        self.scenes.replace(scene);
+        self.enter(scene);
+    }
+
+    // The scene below stays as it is until the overlay is popped
+    fn push_scene(&mut self, scene: Scene) {
+        if self.scenes.push(scene) {
+            self.enter(scene);
+        }
+    }
+
+    fn pop_scene(&mut self) {
+        if let Some(scene) = self.scenes.pop() {
+            self.leave(scene);
+        }
+    }
+
+    // Drops every overlay and puts `scene` at the bottom
+    pub(super) fn reset_scenes(&mut self, scene: Scene) {
+        while self.scenes.below_top().is_some() {
+            self.pop_scene();
+        }
+        self.switch_scene(scene);
+    }
+
+    fn enter(&mut self, scene: Scene) {
+        match scene {
+            Scene::Menu => self.insert_selectors(),
+            Scene::Playing(run) => self.run_started(run),
+            Scene::Paused => self.paused_since = self.time,
+            Scene::GameOver(run) => {
+                // The demo's runs don't count
+                if let (Some(mode), None) = (run.mode(), self.attract) {
+                    let score = match run {
+                        Run::Solo(_, score, _, _) => score,
+                        Run::Versus(_, _) => 0,
+                    };
+                    self.new_best = self.high_scores.record(mode, score);
+                }
+                self.insert_selectors();
+            }
+            Scene::Title | Scene::HighScores | Scene::Settings => {}
+        }
+    }
+
+    fn leave(&mut self, scene: Scene) {
+        match scene {
+            Scene::Menu | Scene::GameOver(_) => {
+                self.init();
+                self.selector_ids = Default::default();
+                self.selected = None;
+            }
+            Scene::Playing(_) => self.run_ended(),
+            // The clock kept going under the overlay
+            Scene::Paused => {
+                let (Some(since), Some(now)) = (self.paused_since.take(), self.time) else {
+                    return;
+                };
+                let Some(paused) = now.checked_duration_since(since) else {
+                    return;
+                };
+                self.run_ends_at = self.run_ends_at.map(|ends_at| ends_at + paused);
+                self.stage_banner_until = self.stage_banner_until.map(|until| until + paused);
+            }
+            Scene::Title | Scene::HighScores | Scene::Settings => {}
+        }
+    }
+
+    // Input for scenes Pudge doesn't walk in, handled on the next tick
+    pub(super) fn scene_input_tick(&mut self) {
+        let Some(control) = self.scene_input.take() else {
+            return;
+        };
+        match (self.scene(), control) {
+            (Scene::Title, ControlEnum::Hook) => self.switch_scene(Scene::Menu),
+            (Scene::Title, ControlEnum::Left) => self.push_scene(Scene::HighScores),
+            (Scene::Title, ControlEnum::Right) => self.push_scene(Scene::Settings),
+            (Scene::Playing(Run::Solo(_, _, _, _)), ControlEnum::Pause) => {
+                self.push_scene(Scene::Paused)
+            }
+            (Scene::Paused, ControlEnum::Pause | ControlEnum::Hook) => self.pop_scene(),
+            (Scene::Paused, ControlEnum::Left) => self.restart(),
+            (Scene::Paused, ControlEnum::Right) => self.reset_scenes(Scene::Menu),
+            (Scene::Settings, ControlEnum::Left) => self.set_difficulty(self.difficulty.easier()),
+            (Scene::Settings, ControlEnum::Right) => self.set_difficulty(self.difficulty.harder()),
+            (Scene::HighScores | Scene::Settings, _) => self.pop_scene(),
+            _ => {}
+        }
+    }
+
+    // Drawn under the overlay on top
+    pub(super) fn draw_scenes(&mut self, display: &mut DisplayEnum) {
+        if let Some(below) = self.scenes.below_top() {
+            self.draw_scene(display, below);
+        }
+        self.draw_scene(display, self.scene());
+    }
+
+    fn draw_scene(&mut self, display: &mut DisplayEnum, scene: Scene) {
+        match scene {
+            Scene::Title => {
+                let splash = Image::new(&SPLASH, Point::zero());
+                draw_image(display, splash);
+            }
+            Scene::Menu | Scene::Playing(_) | Scene::GameOver(_) => self.draw_world(display, scene),
+            Scene::Paused => {
+                draw_centered(display, "PAUSED", 28);
+                draw_centered(display, "< RESTART   MENU >", 38);
+            }
+            Scene::HighScores => {
+                draw_centered(display, "HIGH SCORES", 4);
+                for (row, mode) in MODES.iter().enumerate() {
+                    let mut buffer = TextBuffer::<24>::new();
+                    let _ = write!(buffer, "{} {}", mode.title(), self.high_scores.best(*mode));
+                    draw_centered(display, buffer.as_str(), 20 + 10 * row as i32);
+                }
+            }
+            Scene::Settings => {
+                draw_centered(display, "DIFFICULTY", 4);
+                let mut buffer = TextBuffer::<16>::new();
+                let _ = write!(buffer, "< {} >", self.difficulty.name());
+                draw_centered(display, buffer.as_str(), 28);
+            }
+        }
+    }
+}
+
+// `y` is the top of the line
+fn draw_centered(display: &mut DisplayEnum, text: &str, y: i32) {
+    let text = Text::with_text_style(
+        text,
+        Point::new(64, y),
+        TEXT_STYLE,
+        TextStyleBuilder::new()
+            .alignment(Alignment::Center)
+            .baseline(Baseline::Top)
+            .build(),
+    );
+    draw_text(display, text);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::pudge::{Pudge, Side};
+    use crate::game::testing::{new_game, tick_clock};
+    use crate::game::{Bot, GameEvent, SELECTORS, TICK};
+
+    // Input goes in before the tick that handles it
+    fn press(game: &mut Game, tick: &mut u64, control: ControlEnum) {
+        game.control(Player::One, control);
+        *tick += 1;
+        game.process(tick_clock(*tick)).unwrap();
+    }
+
+    #[test]
+    fn test_overlays_pop_back_to_the_scene_below() {
+        let mut game = new_game();
+        let mut tick = 0;
+        game.process(tick_clock(tick)).unwrap();
+
+        press(&mut game, &mut tick, ControlEnum::Left);
+        assert_eq!(game.scene(), Scene::HighScores);
+        press(&mut game, &mut tick, ControlEnum::Hook);
+        assert_eq!(game.scene(), Scene::Title);
+
+        press(&mut game, &mut tick, ControlEnum::Right);
+        press(&mut game, &mut tick, ControlEnum::Right);
+        assert_eq!(game.scene(), Scene::Settings);
+        assert_eq!(game.score_weights().miss_penalty, 20);
+        press(&mut game, &mut tick, ControlEnum::Hook);
+        assert_eq!(game.scene(), Scene::Title);
+
+        press(&mut game, &mut tick, ControlEnum::Hook);
+        assert_eq!(game.scene(), Scene::Menu);
+        assert_eq!(game.object_handler.len(), SELECTORS.len());
+        assert!(game
+            .drain_events()
+            .any(|event| event == GameEvent::SceneChanged(Scene::Title, Scene::Menu)));
+    }
+
+    #[test]
+    fn test_pause_holds_the_clock_and_scenes_release_their_objects() {
+        let mut game = new_game();
+        let mut tick = 0;
+        game.process(tick_clock(tick)).unwrap();
+        game.start_run(Mode::TimeAttack);
+        while game.object_handler.len() == 0 {
+            tick += 1;
+            game.process(tick_clock(tick)).unwrap();
+        }
+
+        press(&mut game, &mut tick, ControlEnum::Pause);
+        assert_eq!(game.scene(), Scene::Paused);
+        let alive = game.object_handler.len();
+        // Longer than the whole run
+        tick += 2 * 60_000_000 / TICK.to_micros();
+        game.process(tick_clock(tick)).unwrap();
+        assert_eq!(game.object_handler.len(), alive);
+
+        press(&mut game, &mut tick, ControlEnum::Hook);
+        assert!(game.scene().is_playing());
+
+        game.end_run();
+        assert!(matches!(
+            game.scene(),
+            Scene::GameOver(Run::Solo(Mode::TimeAttack, _, _, _))
+        ));
+        // Only the selectors are left
+        assert_eq!(game.object_handler.len(), SELECTORS.len());
+        game.start_run(Mode::Classic);
+        assert_eq!(game.object_handler.len(), 0);
+    }
+
+    #[test]
+    fn test_restarts_start_from_a_clean_world() {
+        let mut game = new_game();
+        let mut tick = 0;
+        game.process(tick_clock(tick)).unwrap();
+        let fresh = Pudge::new(Side::Bottom).body_box();
+        game.start_run(Mode::Classic);
+        for _ in 0..5 {
+            assert_eq!(game.scene(), Scene::Playing(Run::solo(Mode::Classic)));
+            assert_eq!(game.object_handler.len(), 0);
+            assert_eq!(game.pudge(Player::One).body_box(), fresh);
+            // Walk off and throw once a few creeps are out
+            while game.object_handler.len() < 2 {
+                assert!(tick < 100_000, "nothing spawned");
+                press(&mut game, &mut tick, ControlEnum::Right);
+            }
+            press(&mut game, &mut tick, ControlEnum::Hook);
+            assert!(!game.pudge(Player::One).is_walking());
+            game.restart();
+        }
+    }
+
+    #[test]
+    fn test_game_over_only_has_the_selectors() {
+        let mut game = new_game();
+        let mut tick = 0;
+        game.process(tick_clock(tick)).unwrap();
+        let bot = Bot::new(Player::One);
+        game.start_run(Mode::Classic);
+        for _ in 0..3 {
+            assert!(game.scene().is_playing());
+            assert_eq!(game.object_handler.len(), 0);
+            // The run ends with a creep on the hook
+            loop {
+                assert!(tick < 100_000, "never hooked a creep");
+                let control = bot.control(&game);
+                press(&mut game, &mut tick, control);
+                if game
+                    .drain_events()
+                    .any(|event| matches!(event, GameEvent::Hooked(_, _)))
+                {
+                    break;
+                }
+            }
+            game.end_run();
+            assert!(matches!(game.scene(), Scene::GameOver(_)));
+            assert_eq!(game.object_handler.len(), SELECTORS.len());
+            assert!(game.pudge(Player::One).is_walking());
+
+            // Nothing reeled in from the run starts the next one
+            for _ in 0..200 {
+                press(&mut game, &mut tick, ControlEnum::None);
+            }
+            assert!(matches!(game.scene(), Scene::GameOver(_)));
+            while !game.scene().is_playing() {
+                assert!(tick < 100_000, "never picked a selector");
+                let control = bot.control(&game);
+                press(&mut game, &mut tick, control);
+            }
+        }
+    }
+}
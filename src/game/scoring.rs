@@ -0,0 +1,264 @@
+// A hooked creep of Pudge's own side (Radiant from the bottom, Dire from the
+// top in versus) is worth
+//
+//   reward_weight * reward + speed_weight * |speed| + height_weight * height
+//
+// rounded down, where `reward` starts at 100 and drains by one every tick the
+// creep is alive and `height` is how far from Pudge's edge of the screen it got
+// hooked, `DISPLAY_HEIGHT - y` from the bottom and `y` from the top. Hooks from further than
+// `LONG_HOOK_DISTANCE` and hooks on the fastest creep on screen add their
+// bonus on top, then the total is multiplied by the combo. The other side's
+// creeps are worth nothing and break the combo, same as a miss or a creep getting away.
+// A miss also costs `miss_penalty`. Scores saturate instead of overflowing.
+
+use super::{
+    collisions::CollisionRectangle, object::GameObject, pudge::Side, GameDifficultyEnum, Hookable,
+    ObjectHandler, Score,
+};
+use crate::graphics::display::DISPLAY_HEIGHT;
+
+const MAX_MULTIPLIER: u8 = 5;
+// Upper lane hooks clear this from Pudge's body
+const LONG_HOOK_DISTANCE: f32 = 32.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreWeights {
+    pub reward: f32,
+    pub speed: f32,
+    pub height: f32,
+    pub long_hook_bonus: u32,
+    pub fastest_creep_bonus: u32,
+    pub miss_penalty: u32,
+}
+
+impl ScoreWeights {
+    pub(crate) const fn for_difficulty(difficulty: GameDifficultyEnum) -> Self {
+        match difficulty {
+            GameDifficultyEnum::Easy => ScoreWeights {
+                reward: 1.0,
+                speed: 1.0,
+                height: 1.0,
+                long_hook_bonus: 50,
+                fastest_creep_bonus: 50,
+                miss_penalty: 5,
+            },
+            GameDifficultyEnum::Medium => ScoreWeights {
+                reward: 1.0,
+                speed: 1.0,
+                height: 1.0,
+                long_hook_bonus: 50,
+                fastest_creep_bonus: 50,
+                miss_penalty: 10,
+            },
+            GameDifficultyEnum::Hard => ScoreWeights {
+                reward: 1.0,
+                speed: 10.0,
+                height: 1.0,
+                long_hook_bonus: 75,
+                fastest_creep_bonus: 75,
+                miss_penalty: 20,
+            },
+            GameDifficultyEnum::Dendi => ScoreWeights {
+                reward: 1.0,
+                speed: 20.0,
+                height: 2.0,
+                long_hook_bonus: 100,
+                fastest_creep_bonus: 100,
+                miss_penalty: 50,
+            },
+        }
+    }
+
+    // Before bonuses and the combo
+    pub fn base_points(&self, hooked: &GameObject, side: Side) -> u32 {
+        if hooked.game_type != Hookable::Creep(side.creep()) {
+            return 0;
+        }
+        let height = match side {
+            Side::Bottom => DISPLAY_HEIGHT as f32 - hooked.location().y,
+            Side::Top => hooked.location().y,
+        }
+        .max(0.0);
+        let points = self.reward * hooked.reward() as f32
+            + self.speed * hooked.velocity().x.abs()
+            + self.height * height;
+        // Saturates, NaN ends up as 0
+        points as u32
+    }
+}
+
+// `u32` points as a change to the score, clamped instead of wrapping
+pub fn to_score(points: u32) -> Score {
+    points.min(Score::MAX as u32) as Score
+}
+
+#[derive(Debug, Default)]
+pub struct Combo {
+    streak: u8,
+}
+
+impl Combo {
+    pub fn multiplier(&self) -> u8 {
+        self.streak.clamp(1, MAX_MULTIPLIER)
+    }
+
+    pub fn reset(&mut self) {
+        self.streak = 0;
+    }
+
+    // Points for `hooked` with the bonuses and the raised multiplier.
+    // `hooked` has to be out of `objects` already.
+    pub fn hit(
+        &mut self,
+        weights: &ScoreWeights,
+        hooked: &GameObject,
+        side: Side,
+        objects: &ObjectHandler,
+        pudge_box: &CollisionRectangle,
+    ) -> u32 {
+        self.streak = self.streak.saturating_add(1);
+
+        let mut points = weights.base_points(hooked, side);
+        if pudge_box.distance_to(hooked.location().into()) >= LONG_HOOK_DISTANCE {
+            points = points.saturating_add(weights.long_hook_bonus);
+        }
+        let speed = hooked.velocity().x.abs();
+        let mut others = objects.iter().peekable();
+        if others.peek().is_some() && others.all(|other| other.velocity().x.abs() <= speed) {
+            points = points.saturating_add(weights.fastest_creep_bonus);
+        }
+        points.saturating_mul(self.multiplier() as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controls::Player;
+    use crate::game::{Creep, Game, GameError, GamePoint, Mode, Run, Scene};
+    use nalgebra::Vector2;
+
+    const MEDIUM: ScoreWeights = ScoreWeights::for_difficulty(GameDifficultyEnum::Medium);
+    const HARD: ScoreWeights = ScoreWeights::for_difficulty(GameDifficultyEnum::Hard);
+
+    fn pudge_box() -> CollisionRectangle {
+        CollisionRectangle::new(Vector2::new(64.0, 51.0), Vector2::new(9.0, 13.0))
+    }
+
+    #[test]
+    fn test_base_points_table() {
+        // weights, side, creep, y, speed, expected
+        let table = [
+            (MEDIUM, Side::Bottom, Creep::Radiant, 28, 0.0, 100 + 36),
+            (MEDIUM, Side::Bottom, Creep::Radiant, 28, 1.0, 100 + 1 + 36),
+            // Walking left is worth the same as walking right
+            (MEDIUM, Side::Bottom, Creep::Radiant, 28, -1.0, 100 + 1 + 36),
+            (MEDIUM, Side::Bottom, Creep::Radiant, 10, 2.5, 100 + 2 + 54),
+            (MEDIUM, Side::Bottom, Creep::Radiant, 10, -2.5, 100 + 2 + 54),
+            // Below the screen doesn't take points away
+            (MEDIUM, Side::Bottom, Creep::Radiant, 70, -1.0, 100 + 1),
+            (MEDIUM, Side::Bottom, Creep::Dire, 28, 1.0, 0),
+            (HARD, Side::Bottom, Creep::Radiant, 28, -1.5, 100 + 15 + 36),
+            // Versus player two counts height from the top and hooks Dire
+            (MEDIUM, Side::Top, Creep::Dire, 10, 1.0, 100 + 1 + 10),
+            (MEDIUM, Side::Top, Creep::Radiant, 10, 1.0, 0),
+        ];
+        for (weights, side, creep, y, speed, expected) in table {
+            let object = GameObject::make_creep(GamePoint::new(60, y), creep, speed);
+            assert_eq!(
+                weights.base_points(&object, side),
+                expected,
+                "{:?} from {:?} at y {} with speed {}",
+                creep,
+                side,
+                y,
+                speed
+            );
+        }
+    }
+
+    #[test]
+    fn test_streak_multiplies_and_resets() {
+        let objects = ObjectHandler::new();
+        let creep = GameObject::make_creep(GamePoint::new(60, 28), Creep::Radiant, 0.0);
+        let base = MEDIUM.base_points(&creep, Side::Bottom);
+
+        let mut combo = Combo::default();
+        assert_eq!(
+            combo.hit(&MEDIUM, &creep, Side::Bottom, &objects, &pudge_box()),
+            base
+        );
+        assert_eq!(
+            combo.hit(&MEDIUM, &creep, Side::Bottom, &objects, &pudge_box()),
+            base * 2
+        );
+        combo.reset();
+        assert_eq!(combo.multiplier(), 1);
+        for _ in 0..10 {
+            combo.hit(&MEDIUM, &creep, Side::Bottom, &objects, &pudge_box());
+        }
+        assert_eq!(combo.multiplier(), MAX_MULTIPLIER);
+    }
+
+    #[test]
+    fn test_long_and_fastest_bonuses() {
+        let mut objects = ObjectHandler::new();
+        objects
+            .insert(GameObject::make_creep(
+                GamePoint::new(0, 28),
+                Creep::Radiant,
+                -0.5,
+            ))
+            .unwrap();
+        let far = GameObject::make_creep(GamePoint::new(64, 10), Creep::Radiant, -1.0);
+
+        let mut combo = Combo::default();
+        let points = combo.hit(&MEDIUM, &far, Side::Bottom, &objects, &pudge_box());
+        assert_eq!(
+            points,
+            MEDIUM.base_points(&far, Side::Bottom)
+                + MEDIUM.long_hook_bonus
+                + MEDIUM.fastest_creep_bonus
+        );
+    }
+
+    #[test]
+    fn test_score_saturates_and_needs_a_run() {
+        assert_eq!(to_score(u32::MAX), Score::MAX);
+
+        let mut run = Run::Solo(Mode::Classic, Score::MAX - 1, 3, 1);
+        assert!(run.add_score(Player::One, 10).is_ok());
+        assert_eq!(run, Run::Solo(Mode::Classic, Score::MAX, 3, 1));
+        run = Run::Solo(Mode::Classic, 5, 3, 1);
+        assert!(run.add_score(Player::One, -10).is_ok());
+        assert_eq!(run, Run::Solo(Mode::Classic, 0, 3, 1));
+
+        let mut game = Game::default();
+        assert_eq!(
+            game.add_score(Player::One, 10),
+            Err(GameError::NoRun(Scene::Title))
+        );
+    }
+
+    #[test]
+    fn test_versus_keeps_score_and_hp_per_player() {
+        let mut run = Run::Versus([0; 2], [2; 2]);
+        assert!(run.add_score(Player::Two, 100).is_ok());
+        assert!(run.add_score(Player::One, -10).is_ok());
+        assert!(run.damage(Player::One).is_ok());
+        assert_eq!(run, Run::Versus([0, 100], [1, 2]));
+        assert!(!run.is_over());
+
+        assert!(run.damage(Player::One).is_ok());
+        assert!(run.is_over());
+        assert_eq!(run.winner(), Some(Player::Two));
+
+        let single = Run::solo(Mode::Classic);
+        run = single;
+        assert_eq!(
+            run.add_score(Player::Two, 10),
+            Err(GameError::NotPlaying(Player::Two))
+        );
+        assert_eq!(run, single);
+    }
+}
@@ -1,88 +1,473 @@
-use super::{object::GameObject, GamePoint, Id, Instant, Speed};
+use core::fmt;
+
+use super::{
+    object::{GameObject, OBJECT_CAPACITY},
+    waves::{Heading, Lane, Wave, WaveTable, LOWER_LANE_Y, MAX_WAVES, UPPER_LANE_Y},
+    Creep, GamePoint, Instant, Speed,
+};
+use crate::graphics::display::DISPLAY_HEIGHT;
 
 use rand::prelude::*;
 
 pub struct Spawner {
-    spawn_rate_ms: u32,
-    base_speed: Speed,
+    waves: WaveTable,
+    wave: usize,
+    spawned_in_wave: u8,
+    cycle: u32,
+    alternate_right: bool,
     rng: SmallRng,
-    spawn_axes: [u8; 2],
-    last_id: Id,
     last_spawn: Instant,
+    jitter_percent: u8,
+    // Lower, upper
+    lanes: [u8; 2],
+    upper_lane_chance: f64,
+    speed_ramp: Speed,
+    min_interval_ms: u32,
+    creep_weights: Option<[u8; 2]>,
+    max_objects: usize,
 }
 
+// Without `waves` the spawner runs one endless wave made from
+// `spawn_interval_ms`/`creep_per_m` and `base_speed`, ramping up every spawn
 pub struct SpawnerBuilder {
-    creep_per_m: u8,
     seed: u64,
+    creep_per_m: Option<u8>,
+    spawn_interval_ms: u32,
     base_speed: Speed,
+    waves: Option<WaveTable>,
+    too_many_waves: bool,
+    jitter_percent: u8,
+    lanes: [u8; 2],
+    upper_lane_chance: f64,
+    speed_ramp: Speed,
+    min_interval_ms: u32,
+    creep_weights: Option<[u8; 2]>,
+    max_objects: usize,
 }
 
-impl Spawner {
-    pub fn from_config(config: SpawnerBuilder) -> Self {
-        let spawn_rate_ms = 6000 / config.creep_per_m as u32;
-        Self {
-            spawn_rate_ms,
-            base_speed: config.base_speed,
-            rng: SmallRng::seed_from_u64(config.seed),
-            spawn_axes: [28, 10],
-            last_id: 0,
-            last_spawn: Instant::from_ticks(0),
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpawnerError {
+    ZeroCreepRate,
+    ZeroInterval,
+    Speed,
+    SpeedRamp,
+    Jitter,
+    Lane,
+    LaneChance,
+    CreepWeights,
+    MaxObjects,
+    NoWaves,
+    TooManyWaves,
+    // Index into the wave table
+    Wave(usize),
+}
+
+impl fmt::Display for SpawnerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpawnerError::ZeroCreepRate => write!(f, "creep_per_m must be above 0"),
+            SpawnerError::ZeroInterval => write!(f, "spawn interval must be above 0"),
+            SpawnerError::Speed => write!(f, "base speed must be above 0"),
+            SpawnerError::SpeedRamp => write!(f, "speed ramp can't be negative"),
+            SpawnerError::Jitter => write!(f, "jitter can't be over 100%"),
+            SpawnerError::Lane => write!(f, "lanes must be on screen"),
+            SpawnerError::LaneChance => write!(f, "upper lane chance must be within 0..=1"),
+            SpawnerError::CreepWeights => write!(f, "creep weights can't both be 0"),
+            SpawnerError::MaxObjects => {
+                write!(f, "max objects must be within 1..={}", OBJECT_CAPACITY)
+            }
+            SpawnerError::NoWaves => write!(f, "no waves"),
+            SpawnerError::TooManyWaves => write!(f, "more than {} waves", MAX_WAVES),
+            SpawnerError::Wave(idx) => write!(f, "wave {} is invalid", idx),
         }
     }
-    pub fn new(creep_per_m: u8, seed: u64, base_speed: Speed) -> Self {
-        let spawn_rate_ms = 6000 / creep_per_m as u32;
+}
+
+// Overrides on top of a stage's spawner, for custom modes. `None` keeps
+// the stage's own setting.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct SpawnerTweaks {
+    pub upper_lane_chance: Option<f64>,
+    // Lower lane creeps walk the upper lane too
+    pub upper_lane_only: bool,
+    pub speed_ramp: Option<Speed>,
+    pub min_interval_ms: Option<u32>,
+    pub jitter_percent: Option<u8>,
+    pub creep_weights: Option<[u8; 2]>,
+    pub max_objects: Option<usize>,
+}
+
+impl SpawnerTweaks {
+    pub fn apply(&self, mut builder: SpawnerBuilder) -> SpawnerBuilder {
+        if let Some(chance) = self.upper_lane_chance {
+            builder = builder.upper_lane_chance(chance);
+        }
+        if self.upper_lane_only {
+            builder = builder.lanes(UPPER_LANE_Y, UPPER_LANE_Y);
+        }
+        if let Some(ramp) = self.speed_ramp {
+            builder = builder.speed_ramp(ramp);
+        }
+        if let Some(interval_ms) = self.min_interval_ms {
+            builder = builder.min_interval_ms(interval_ms);
+        }
+        if let Some(percent) = self.jitter_percent {
+            builder = builder.jitter_percent(percent);
+        }
+        if let Some([radiant, dire]) = self.creep_weights {
+            builder = builder.creep_weights(radiant, dire);
+        }
+        if let Some(max_objects) = self.max_objects {
+            builder = builder.max_objects(max_objects);
+        }
+        builder
+    }
+}
+
+impl Default for SpawnerBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SpawnerBuilder {
+    pub fn new() -> Self {
         Self {
-            spawn_rate_ms,
-            base_speed,
-            rng: SmallRng::seed_from_u64(seed),
-            spawn_axes: [28, 10],
-            last_id: 0,
+            seed: 0,
+            creep_per_m: None,
+            spawn_interval_ms: 6000,
+            base_speed: 0.65,
+            waves: None,
+            too_many_waves: false,
+            jitter_percent: 33,
+            lanes: [LOWER_LANE_Y, UPPER_LANE_Y],
+            upper_lane_chance: 0.3,
+            speed_ramp: 0.25,
+            min_interval_ms: 500,
+            creep_weights: None,
+            max_objects: OBJECT_CAPACITY,
+        }
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    // Shorthand for `spawn_interval_ms(6000 / creep_per_m)`
+    pub fn creep_per_m(mut self, creep_per_m: u8) -> Self {
+        self.creep_per_m = Some(creep_per_m);
+        self
+    }
+
+    pub fn spawn_interval_ms(mut self, interval_ms: u32) -> Self {
+        self.creep_per_m = None;
+        self.spawn_interval_ms = interval_ms;
+        self
+    }
+
+    pub fn base_speed(mut self, speed: Speed) -> Self {
+        self.base_speed = speed;
+        self
+    }
+
+    pub fn waves(mut self, waves: &[Wave]) -> Self {
+        self.too_many_waves = waves.len() > MAX_WAVES;
+        self.waves = Some(waves.iter().copied().take(MAX_WAVES).collect());
+        self
+    }
+
+    // Up to this share of the interval is randomly taken off each spawn
+    pub fn jitter_percent(mut self, percent: u8) -> Self {
+        self.jitter_percent = percent;
+        self
+    }
+
+    pub fn lanes(mut self, lower_y: u8, upper_y: u8) -> Self {
+        self.lanes = [lower_y, upper_y];
+        self
+    }
+
+    // Used by `Lane::Any` waves
+    pub fn upper_lane_chance(mut self, chance: f64) -> Self {
+        self.upper_lane_chance = chance;
+        self
+    }
+
+    // Added to the speed on every pass through the waves,
+    // intervals lose a tenth each pass down to `min_interval_ms`
+    pub fn speed_ramp(mut self, speed: Speed) -> Self {
+        self.speed_ramp = speed;
+        self
+    }
+
+    pub fn min_interval_ms(mut self, interval_ms: u32) -> Self {
+        self.min_interval_ms = interval_ms;
+        self
+    }
+
+    // Rolls every creep's type, overriding the waves' own
+    pub fn creep_weights(mut self, radiant: u8, dire: u8) -> Self {
+        self.creep_weights = Some([radiant, dire]);
+        self
+    }
+
+    // Spawning waits while this many objects are alive
+    pub fn max_objects(mut self, max_objects: usize) -> Self {
+        self.max_objects = max_objects;
+        self
+    }
+
+    pub fn build(self) -> Result<Spawner, SpawnerError> {
+        let interval_ms = match self.creep_per_m {
+            Some(0) => return Err(SpawnerError::ZeroCreepRate),
+            Some(creep_per_m) => 6000 / creep_per_m as u32,
+            None => self.spawn_interval_ms,
+        };
+        if interval_ms == 0 || self.min_interval_ms == 0 {
+            return Err(SpawnerError::ZeroInterval);
+        }
+        if !(self.base_speed > 0.0 && self.base_speed.is_finite()) {
+            return Err(SpawnerError::Speed);
+        }
+        if !(self.speed_ramp >= 0.0 && self.speed_ramp.is_finite()) {
+            return Err(SpawnerError::SpeedRamp);
+        }
+        if self.jitter_percent > 100 {
+            return Err(SpawnerError::Jitter);
+        }
+        if self.lanes.iter().any(|&y| y as u32 >= DISPLAY_HEIGHT) {
+            return Err(SpawnerError::Lane);
+        }
+        if !(0.0..=1.0).contains(&self.upper_lane_chance) {
+            return Err(SpawnerError::LaneChance);
+        }
+        if self.creep_weights == Some([0, 0]) {
+            return Err(SpawnerError::CreepWeights);
+        }
+        if self.max_objects == 0 || self.max_objects > OBJECT_CAPACITY {
+            return Err(SpawnerError::MaxObjects);
+        }
+
+        let waves = match self.waves {
+            Some(_) if self.too_many_waves => return Err(SpawnerError::TooManyWaves),
+            Some(waves) if waves.is_empty() => return Err(SpawnerError::NoWaves),
+            Some(waves) => waves,
+            None => WaveTable::from_slice(&[Wave {
+                lane: Lane::Any,
+                heading: Heading::Any,
+                count: 1,
+                interval_ms,
+                creep: Creep::Radiant,
+                speed: self.base_speed,
+            }])
+            .unwrap(),
+        };
+        if let Some(idx) = waves.iter().position(|wave| !wave.is_valid()) {
+            return Err(SpawnerError::Wave(idx));
+        }
+
+        Ok(Spawner {
+            waves,
+            wave: 0,
+            spawned_in_wave: 0,
+            cycle: 0,
+            alternate_right: false,
+            rng: SmallRng::seed_from_u64(self.seed),
             last_spawn: Instant::from_ticks(0),
+            jitter_percent: self.jitter_percent,
+            lanes: self.lanes,
+            upper_lane_chance: self.upper_lane_chance,
+            speed_ramp: self.speed_ramp,
+            min_interval_ms: self.min_interval_ms,
+            creep_weights: self.creep_weights,
+            max_objects: self.max_objects,
+        })
+    }
+}
+
+impl Spawner {
+    fn current_wave(&self) -> &Wave {
+        &self.waves[self.wave]
+    }
+
+    pub fn base_speed(&self) -> Speed {
+        self.current_wave().speed + self.speed_ramp * self.cycle as Speed
+    }
+
+    pub fn spawn_rate_ms(&self) -> u32 {
+        let mut rate = self.current_wave().interval_ms;
+        // Past a few cycles it's at the floor anyway
+        for _ in 0..self.cycle.min(32) {
+            rate -= rate / 10;
         }
+        rate.max(self.min_interval_ms)
     }
 
     fn max_spawn_deviation(&self) -> u32 {
-        self.spawn_rate_ms / 3
+        self.spawn_rate_ms() * self.jitter_percent as u32 / 100
     }
 
     fn spawn_check(&mut self, time: Instant) -> bool {
-        let since_last_spawn = time.checked_duration_since(self.last_spawn).unwrap();
-        let spawn_timer =
-            since_last_spawn.to_millis() + self.rng.gen_range(0..self.max_spawn_deviation()) as u64;
-        spawn_timer > self.spawn_rate_ms.into()
+        let Some(since_last_spawn) = time.checked_duration_since(self.last_spawn) else {
+            // Clock went backwards, count from now
+            self.last_spawn = time;
+            return false;
+        };
+        let spawn_timer = since_last_spawn.to_millis()
+            + self.rng.gen_range(0..=self.max_spawn_deviation()) as u64;
+        spawn_timer > self.spawn_rate_ms().into()
     }
 
     fn random_object(&mut self) -> GameObject {
-        let id = self.last_id + 1;
-        self.last_id = id;
-        let upper_axes = self.rng.gen_bool(0.3);
-        let go_right = self.rng.gen_bool(0.5);
-        let y = if upper_axes {
-            self.spawn_axes[1]
-        } else {
-            self.spawn_axes[0]
+        let wave = *self.current_wave();
+        let upper_lane = match wave.lane {
+            Lane::Lower => false,
+            Lane::Upper => true,
+            Lane::Any => self.rng.gen_bool(self.upper_lane_chance),
+        };
+        let go_right = match wave.heading {
+            Heading::Right => true,
+            Heading::Left => false,
+            Heading::Any => self.rng.gen_bool(0.5),
+            Heading::Alternate => {
+                self.alternate_right = !self.alternate_right;
+                self.alternate_right
+            }
         };
+        let creep = match self.creep_weights {
+            Some([radiant, dire]) => {
+                if self.rng.gen_range(0..radiant as u16 + dire as u16) < radiant as u16 {
+                    Creep::Radiant
+                } else {
+                    Creep::Dire
+                }
+            }
+            None => wave.creep,
+        };
+        let y = self.lanes[upper_lane as usize];
         let x = if go_right { 0 } else { 128 };
         let speed = if go_right {
-            self.base_speed + self.rng.gen_range(0..1) as f32
+            self.base_speed()
         } else {
-            -self.base_speed - self.rng.gen_range(0..1) as f32
+            -self.base_speed()
         };
 
         let location = GamePoint::new(x, y.into());
+        GameObject::make_creep(location, creep, speed)
+    }
 
-        //TODO: other creep types etc.
-        let alliegiance = super::Creep::Radiant;
-
-        GameObject::make_creep(id, location, alliegiance, speed)
+    fn advance_wave(&mut self) {
+        self.spawned_in_wave += 1;
+        if self.spawned_in_wave < self.current_wave().count {
+            return;
+        }
+        self.spawned_in_wave = 0;
+        self.wave += 1;
+        if self.wave == self.waves.len() {
+            self.wave = 0;
+            self.cycle += 1;
+        }
     }
 
-    pub fn try_spawn(&mut self, time: Instant) -> Option<GameObject> {
-        if !self.spawn_check(time) {
+    // `alive` is how many objects are on screen right now
+    pub fn try_spawn(&mut self, time: Instant, alive: usize) -> Option<GameObject> {
+        if alive >= self.max_objects || !self.spawn_check(time) {
             return None;
         }
         self.last_spawn = time;
-        self.base_speed += 0.25;
-        Some(self.random_object())
+        let object = self.random_object();
+        self.advance_wave();
+        Some(object)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Hookable;
+
+    #[test]
+    fn test_waves_step_and_escalate() {
+        let waves = [
+            Wave {
+                lane: Lane::Upper,
+                heading: Heading::Right,
+                count: 2,
+                interval_ms: 1000,
+                creep: Creep::Radiant,
+                speed: 1.0,
+            },
+            Wave {
+                lane: Lane::Lower,
+                heading: Heading::Alternate,
+                count: 1,
+                interval_ms: 2000,
+                creep: Creep::Dire,
+                speed: 0.5,
+            },
+        ];
+        let mut spawner = SpawnerBuilder::new().seed(1).waves(&waves).build().unwrap();
+        let mut time = Instant::from_ticks(0);
+        let mut spawn = || {
+            // Past any jitter so every call spawns
+            time += fugit::MicrosDurationU64::secs(10);
+            spawner.try_spawn(time, 0).unwrap()
+        };
+        let (a, b, c, d) = (spawn(), spawn(), spawn(), spawn());
+
+        for creep in [a, b] {
+            assert_eq!(creep.game_type, Hookable::Creep(Creep::Radiant));
+            assert_eq!(creep.velocity().x, 1.0);
+        }
+        assert_eq!(c.game_type, Hookable::Creep(Creep::Dire));
+        assert_eq!(c.velocity().x, 0.5);
+
+        // Second pass through the table
+        assert_eq!(spawner.base_speed(), 1.0 + 0.25);
+        assert_eq!(spawner.spawn_rate_ms(), 900);
+        assert_eq!(d.velocity().x, 1.25);
+    }
+
+    #[test]
+    fn test_builder_rejects_invalid_configs() {
+        let build = |builder: SpawnerBuilder| builder.build().err();
+        assert_eq!(
+            build(SpawnerBuilder::new().creep_per_m(0)),
+            Some(SpawnerError::ZeroCreepRate)
+        );
+        assert_eq!(
+            build(SpawnerBuilder::new().spawn_interval_ms(0)),
+            Some(SpawnerError::ZeroInterval)
+        );
+        assert_eq!(
+            build(SpawnerBuilder::new().base_speed(-1.0)),
+            Some(SpawnerError::Speed)
+        );
+        assert_eq!(
+            build(SpawnerBuilder::new().lanes(28, 64)),
+            Some(SpawnerError::Lane)
+        );
+        assert_eq!(
+            build(SpawnerBuilder::new().creep_weights(0, 0)),
+            Some(SpawnerError::CreepWeights)
+        );
+        assert_eq!(
+            build(SpawnerBuilder::new().max_objects(OBJECT_CAPACITY + 1)),
+            Some(SpawnerError::MaxObjects)
+        );
+        assert_eq!(
+            build(SpawnerBuilder::new().waves(&[])),
+            Some(SpawnerError::NoWaves)
+        );
+        assert!(SpawnerBuilder::new().creep_per_m(2).build().is_ok());
+    }
+
+    #[test]
+    fn test_max_objects_holds_spawns() {
+        let mut spawner = SpawnerBuilder::new().max_objects(2).build().unwrap();
+        let time = Instant::from_ticks(0) + fugit::MicrosDurationU64::secs(60);
+        assert!(spawner.try_spawn(time, 2).is_none());
+        assert!(spawner.try_spawn(time, 1).is_some());
     }
 }
@@ -0,0 +1,170 @@
+use embedded_graphics::{
+    prelude::*,
+    primitives::{Line, Triangle},
+};
+
+use super::{
+    spawner::SpawnerBuilder,
+    waves::{Wave, WaveTable},
+    Score, Speed,
+};
+use crate::graphics::{
+    display::DisplayEnum,
+    image::draw_primitive,
+    resources::{SCREEN_WIDTH, WHITE_LINE},
+};
+
+// 1-based, as shown on the "STAGE N" banner
+pub type StageNr = u8;
+
+// Drawn behind everything, in the gaps between the lanes and Pudge
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Decoration {
+    Plain,
+    LaneMarks,
+    River,
+    Forest,
+}
+
+pub struct Stage {
+    // Score in a solo run that moves play to this stage
+    pub threshold: Score,
+    // Added to every wave's speed
+    pub speed_bonus: Speed,
+    pub creep_weights: Option<[u8; 2]>,
+    pub max_objects: usize,
+    pub decoration: Decoration,
+}
+
+pub const STAGES: [Stage; 4] = [
+    Stage {
+        threshold: 0,
+        speed_bonus: 0.0,
+        creep_weights: None,
+        max_objects: 6,
+        decoration: Decoration::Plain,
+    },
+    Stage {
+        threshold: 1000,
+        speed_bonus: 0.2,
+        creep_weights: Some([9, 1]),
+        max_objects: 8,
+        decoration: Decoration::LaneMarks,
+    },
+    Stage {
+        threshold: 2500,
+        speed_bonus: 0.4,
+        creep_weights: Some([4, 1]),
+        max_objects: 10,
+        decoration: Decoration::River,
+    },
+    Stage {
+        threshold: 5000,
+        speed_bonus: 0.6,
+        creep_weights: Some([3, 1]),
+        max_objects: 12,
+        decoration: Decoration::Forest,
+    },
+];
+
+impl Stage {
+    // Past the last one play stays there
+    pub fn get(nr: StageNr) -> &'static Stage {
+        let idx = (nr.max(1) as usize - 1).min(STAGES.len() - 1);
+        &STAGES[idx]
+    }
+
+    pub fn next(nr: StageNr) -> Option<&'static Stage> {
+        STAGES.get(nr as usize)
+    }
+
+    pub fn spawner(&self, waves: &[Wave]) -> SpawnerBuilder {
+        let waves: WaveTable = waves
+            .iter()
+            .map(|wave| Wave {
+                speed: wave.speed + self.speed_bonus,
+                ..*wave
+            })
+            .collect();
+        let builder = SpawnerBuilder::new()
+            .waves(&waves)
+            .max_objects(self.max_objects);
+        match self.creep_weights {
+            Some([radiant, dire]) => builder.creep_weights(radiant, dire),
+            None => builder,
+        }
+    }
+}
+
+const LANE_GAP_Y: i32 = 24;
+const GROUND_Y: i32 = 44;
+
+impl Decoration {
+    pub fn draw(&self, display: &mut DisplayEnum) {
+        match self {
+            Decoration::Plain => {}
+            Decoration::LaneMarks => {
+                for y in [LANE_GAP_Y, GROUND_Y] {
+                    draw_dashes(display, y, 2, 4, 0);
+                }
+            }
+            Decoration::River => {
+                draw_dashes(display, GROUND_Y - 1, 4, 4, 0);
+                draw_dashes(display, GROUND_Y + 1, 4, 4, 4);
+            }
+            Decoration::Forest => {
+                for x in (4..SCREEN_WIDTH as i32).step_by(16) {
+                    let tree = Triangle::new(
+                        Point::new(x, GROUND_Y + 2),
+                        Point::new(x + 2, GROUND_Y - 3),
+                        Point::new(x + 4, GROUND_Y + 2),
+                    )
+                    .into_styled(WHITE_LINE);
+                    draw_primitive(display, &tree);
+                }
+            }
+        }
+    }
+}
+
+fn draw_dashes(display: &mut DisplayEnum, y: i32, dash: i32, gap: i32, offset: i32) {
+    for x in (offset..SCREEN_WIDTH as i32).step_by((dash + gap) as usize) {
+        let line = Line::new(Point::new(x, y), Point::new(x + dash - 1, y)).into_styled(WHITE_LINE);
+        draw_primitive(display, &line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controls::Player;
+    use crate::game::{waves::DEFAULT_WAVES, Game, GameEvent, Instant, Mode, Run, Scene};
+
+    #[test]
+    fn test_every_stage_builds_a_spawner() {
+        for (idx, stage) in STAGES.iter().enumerate() {
+            assert!(stage.spawner(&DEFAULT_WAVES).build().is_ok());
+            if idx > 0 {
+                assert!(stage.threshold > STAGES[idx - 1].threshold);
+            }
+        }
+    }
+
+    #[test]
+    fn test_threshold_moves_to_next_stage() {
+        let mut game = Game::default();
+        game.start_run(Mode::Classic);
+        assert!(game.add_score(Player::One, STAGES[1].threshold).is_ok());
+        assert!(game.process(Instant::from_ticks(0)).is_ok());
+        assert!(game.process(Instant::from_ticks(1_000_000)).is_ok());
+
+        assert_eq!(
+            game.scene(),
+            Scene::Playing(Run::Solo(Mode::Classic, STAGES[1].threshold, 3, 2))
+        );
+        assert!(game
+            .drain_events()
+            .any(|event| event == GameEvent::StageReached(2)));
+        assert!(game.showing_stage_banner());
+    }
+}
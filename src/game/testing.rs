@@ -0,0 +1,22 @@
+// Fixtures shared by the tests of the game and the mode scripts
+
+use super::{Game, Instant, Mode, TICK};
+
+// Game time after `tick` ticks
+pub(crate) fn tick_clock(tick: u64) -> Instant {
+    Instant::from_ticks(tick * TICK.to_micros())
+}
+
+// A fresh game on the title screen
+pub(crate) fn new_game() -> Game {
+    let mut game = Game::default();
+    game.init();
+    game
+}
+
+// A fresh game that's already playing a solo run
+pub(crate) fn game_in_run(mode: Mode) -> Game {
+    let mut game = new_game();
+    game.start_run(mode);
+    game
+}
@@ -0,0 +1,202 @@
+use core::fmt;
+
+use heapless::Vec;
+
+use super::{Creep, Speed};
+
+pub const MAX_WAVES: usize = 16;
+pub type WaveTable = Vec<Wave, MAX_WAVES>;
+
+// Spawn rows, same as the old `spawn_axes`
+pub const LOWER_LANE_Y: u8 = 28;
+pub const UPPER_LANE_Y: u8 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Lane {
+    Lower,
+    Upper,
+    // Upper lane 30% of the time
+    Any,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Heading {
+    Right,
+    Left,
+    Any,
+    // Flips every spawn, starting from the left edge
+    Alternate,
+}
+
+// `count` creeps spawned `interval_ms` apart, then the next wave starts.
+// After the last wave the table starts over, faster.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Wave {
+    pub lane: Lane,
+    pub heading: Heading,
+    pub count: u8,
+    pub interval_ms: u32,
+    pub creep: Creep,
+    pub speed: Speed,
+}
+
+pub const DEFAULT_WAVES: [Wave; 4] = [
+    Wave {
+        lane: Lane::Lower,
+        heading: Heading::Any,
+        count: 3,
+        interval_ms: 6000,
+        creep: Creep::Radiant,
+        speed: 0.65,
+    },
+    Wave {
+        lane: Lane::Any,
+        heading: Heading::Any,
+        count: 4,
+        interval_ms: 5000,
+        creep: Creep::Radiant,
+        speed: 0.8,
+    },
+    Wave {
+        lane: Lane::Upper,
+        heading: Heading::Alternate,
+        count: 3,
+        interval_ms: 4500,
+        creep: Creep::Radiant,
+        speed: 0.9,
+    },
+    Wave {
+        lane: Lane::Any,
+        heading: Heading::Any,
+        count: 5,
+        interval_ms: 4000,
+        creep: Creep::Radiant,
+        speed: 1.0,
+    },
+];
+
+impl Wave {
+    pub fn is_valid(&self) -> bool {
+        self.count > 0 && self.interval_ms > 0 && self.speed > 0.0 && self.speed.is_finite()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WaveError {
+    // Line numbers start at 1
+    Field(usize, &'static str),
+    MissingField(usize, &'static str),
+    TrailingField(usize),
+    TooManyWaves,
+    Empty,
+}
+
+impl fmt::Display for WaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WaveError::Field(line, field) => write!(f, "line {}: invalid {}", line, field),
+            WaveError::MissingField(line, field) => write!(f, "line {}: missing {}", line, field),
+            WaveError::TrailingField(line) => write!(f, "line {}: too many fields", line),
+            WaveError::TooManyWaves => write!(f, "more than {} waves", MAX_WAVES),
+            WaveError::Empty => write!(f, "no waves"),
+        }
+    }
+}
+
+// One wave per line: `<lane> <heading> <count> <interval ms> <creep> <speed>`,
+// e.g. `lower any 3 6000 radiant 0.65`. Blank lines and `#` comments are skipped.
+pub fn parse_waves(text: &str) -> Result<WaveTable, WaveError> {
+    let mut waves = WaveTable::new();
+    for (idx, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let wave = parse_wave(idx + 1, line)?;
+        waves.push(wave).map_err(|_| WaveError::TooManyWaves)?;
+    }
+    if waves.is_empty() {
+        return Err(WaveError::Empty);
+    }
+    Ok(waves)
+}
+
+fn parse_wave(line_nr: usize, line: &str) -> Result<Wave, WaveError> {
+    let mut fields = line.split_whitespace();
+    let mut field =
+        |name: &'static str| fields.next().ok_or(WaveError::MissingField(line_nr, name));
+    let invalid = |name: &'static str| WaveError::Field(line_nr, name);
+
+    let lane = match field("lane")? {
+        "lower" => Lane::Lower,
+        "upper" => Lane::Upper,
+        "any" => Lane::Any,
+        _ => return Err(invalid("lane")),
+    };
+    let heading = match field("heading")? {
+        "right" => Heading::Right,
+        "left" => Heading::Left,
+        "any" => Heading::Any,
+        "alternate" => Heading::Alternate,
+        _ => return Err(invalid("heading")),
+    };
+    let count: u8 = field("count")?.parse().map_err(|_| invalid("count"))?;
+    let interval_ms: u32 = field("interval")?
+        .parse()
+        .map_err(|_| invalid("interval"))?;
+    let creep = match field("creep")? {
+        "radiant" => Creep::Radiant,
+        "dire" => Creep::Dire,
+        _ => return Err(invalid("creep")),
+    };
+    let speed: Speed = field("speed")?.parse().map_err(|_| invalid("speed"))?;
+    if fields.next().is_some() {
+        return Err(WaveError::TrailingField(line_nr));
+    }
+
+    if count == 0 {
+        return Err(invalid("count"));
+    }
+    if interval_ms == 0 {
+        return Err(invalid("interval"));
+    }
+    if !(speed > 0.0 && speed.is_finite()) {
+        return Err(invalid("speed"));
+    }
+    Ok(Wave {
+        lane,
+        heading,
+        count,
+        interval_ms,
+        creep,
+        speed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundled_waves_match_defaults() {
+        let waves = parse_waves(include_str!("../../assets/waves.txt")).unwrap();
+        assert_eq!(waves, DEFAULT_WAVES);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse_waves("lower any 3 6000 radiant").unwrap_err(),
+            WaveError::MissingField(1, "speed")
+        );
+        assert_eq!(
+            parse_waves("# comment\n\nupper left 0 100 dire 1.0").unwrap_err(),
+            WaveError::Field(3, "count")
+        );
+        assert_eq!(
+            parse_waves("lower any 3 6000 radiant 1.0 extra").unwrap_err(),
+            WaveError::TrailingField(1)
+        );
+        assert_eq!(parse_waves("# nothing").unwrap_err(), WaveError::Empty);
+    }
+}
@@ -96,7 +96,7 @@ pub fn clear_display(display: &mut DisplayEnum) {
 }
 
 pub fn get_fps(delta: fugit::Duration<u64, 1, 1000000>) -> u32 {
-    let fps = 1.0 / (delta.to_millis() as f32 / 1_000.0);
+    let fps = 1.0 / (delta.to_micros() as f32 / 1_000_000.0);
     #[cfg(not(target_os = "none"))]
     return fps.floor() as u32;
     #[cfg(target_os = "none")]
@@ -8,4 +8,3 @@ pub fn flush(display: &mut DisplayEnum) {
         _ => panic!("Trying to flush a non-flushable display!"),
     }
 }
-
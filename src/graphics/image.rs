@@ -1,13 +1,21 @@
+use core::fmt::Write;
+
 use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::Line;
 use embedded_graphics::text::renderer::TextRenderer;
 use embedded_graphics::text::Text;
 use embedded_graphics::{
-    image::{Image, ImageDrawable},
+    image::{GetPixel, Image, ImageDrawable, ImageRaw},
     pixelcolor::BinaryColor,
 };
 
 use super::display::{DisplayEnum, DISPLAY_WIDTH};
-use super::resources::TEXT_STYLE;
+use super::resources::{TEXT_STYLE, WHITE_LINE};
+use super::utils::TextBuffer;
+
+// Chain is drawn as links of CHAIN_LINK pixels separated by CHAIN_GAP
+const CHAIN_LINK: usize = 2;
+const CHAIN_GAP: usize = 1;
 
 pub fn draw_text<'a, T>(display: &mut DisplayEnum, text: Text<'a, T>)
 where
@@ -22,7 +30,7 @@ where
         DisplayEnum::WebView(ref mut disp) => {
             text.draw(disp).unwrap();
         }
-        #[cfg(not(target_os = "none"))]
+        #[cfg(not(any(target_os = "none", target_os = "unknown")))]
         DisplayEnum::Simulator(ref mut disp) => {
             text.draw(disp).unwrap();
         }
@@ -30,11 +38,17 @@ where
     }
 }
 
-pub fn draw_hp(display: &mut DisplayEnum, hp: u8) {
-    let hp_str = "<3 ".repeat(hp.into());
+// Right aligned, `y` is the text baseline
+pub fn draw_hp(display: &mut DisplayEnum, hp: u8, y: i32) {
+    let mut hp_str = TextBuffer::<32>::new();
+    for _ in 0..hp {
+        if hp_str.write_str("<3 ").is_err() {
+            break;
+        }
+    }
     let hp_text = Text::with_alignment(
-        &hp_str,
-        Point::new(DISPLAY_WIDTH as i32 - 4, 6),
+        hp_str.as_str(),
+        Point::new(DISPLAY_WIDTH as i32 - 4, y),
         TEXT_STYLE.clone(),
         embedded_graphics::text::Alignment::Right,
     );
@@ -47,7 +61,7 @@ pub fn draw_hp(display: &mut DisplayEnum, hp: u8) {
         DisplayEnum::WebView(ref mut disp) => {
             hp_text.draw(disp).unwrap();
         }
-        #[cfg(not(target_os = "none"))]
+        #[cfg(not(any(target_os = "none", target_os = "unknown")))]
         DisplayEnum::Simulator(ref mut disp) => {
             hp_text.draw(disp).unwrap();
         }
@@ -69,3 +83,72 @@ where
         DisplayEnum::Oled(ref mut phys_disp) => image.draw(phys_disp).unwrap(),
     };
 }
+
+// Upside down `raw`, for sprites facing down
+struct FlippedImage<'a> {
+    raw: &'a ImageRaw<'a, BinaryColor>,
+    position: Point,
+}
+
+impl Drawable for FlippedImage<'_> {
+    type Color = BinaryColor;
+    type Output = ();
+
+    fn draw<D>(&self, target: &mut D) -> Result<Self::Output, D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        let bottom = self.raw.size().height as i32 - 1;
+        let pixels = self.raw.bounding_box().points().filter_map(|point| {
+            let color = self.raw.pixel(point)?;
+            Some(Pixel(
+                self.position + Point::new(point.x, bottom - point.y),
+                color,
+            ))
+        });
+        target.draw_iter(pixels)
+    }
+}
+
+pub fn draw_image_flipped(display: &mut DisplayEnum, raw: &ImageRaw<BinaryColor>, position: Point) {
+    draw_primitive(display, &FlippedImage { raw, position });
+}
+
+pub fn draw_primitive<T>(display: &mut DisplayEnum, primitive: &T)
+where
+    T: Drawable<Color = BinaryColor>,
+{
+    match display {
+        DisplayEnum::Mock(ref mut disp) => {
+            primitive.draw(disp).unwrap();
+        }
+        #[cfg(not(any(target_os = "none", target_os = "unknown")))]
+        DisplayEnum::Simulator(ref mut disp) => {
+            primitive.draw(disp).unwrap();
+        }
+        #[cfg(target_arch = "wasm32")]
+        DisplayEnum::WebView(ref mut disp) => {
+            primitive.draw(disp).unwrap();
+        }
+        #[cfg(target_os = "none")]
+        DisplayEnum::Oled(ref mut phys_disp) => {
+            primitive.draw(phys_disp).unwrap();
+        }
+    };
+}
+
+pub fn draw_chain(display: &mut DisplayEnum, start: Point, end: Point) {
+    let mut link_start: Option<Point> = None;
+    for (idx, point) in Line::new(start, end).points().enumerate() {
+        let link_pos = idx % (CHAIN_LINK + CHAIN_GAP);
+        if link_pos == 0 {
+            link_start = Some(point);
+        }
+        if link_pos == CHAIN_LINK - 1 || point == end {
+            if let Some(link_start) = link_start.take() {
+                let link = Line::new(link_start, point).into_styled(WHITE_LINE);
+                draw_primitive(display, &link);
+            }
+        }
+    }
+}
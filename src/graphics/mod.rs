@@ -1,4 +1,6 @@
 pub mod display;
 pub mod image;
 pub mod resources;
-mod utils;
+#[cfg(not(target_os = "none"))]
+pub mod sprite_pack;
+pub(crate) mod utils;
@@ -1,11 +1,15 @@
 use embedded_graphics::{
+    geometry::OriginDimensions,
     image::ImageRaw,
     mono_font::{ascii, MonoTextStyle, MonoTextStyleBuilder},
     pixelcolor::BinaryColor,
     primitives::PrimitiveStyle,
 };
 
-use crate::game::Creep;
+use crate::game::{Creep, Hookable};
+
+#[cfg(not(target_os = "none"))]
+use super::sprite_pack;
 
 pub const SCREEN_WIDTH: u8 = 128;
 pub const SCREEN_HEIGHT: u8 = 64;
@@ -21,50 +25,100 @@ pub const WHITE_FILL: PrimitiveStyle<BinaryColor> = PrimitiveStyle::with_fill(Bi
 pub const BLACK_FILL: PrimitiveStyle<BinaryColor> = PrimitiveStyle::with_fill(BinaryColor::Off);
 pub const CHAR_WIDTH: usize = 4;
 
-// 18x13
-pub const PUDGE_WIDTH: u8 = 18;
-pub const PUDGE_HEIGHT: u8 = 13;
-pub const PUDGE_BODY: ImageRaw<BinaryColor> =
-    ImageRaw::new(include_bytes!("../../assets/PudgeBody.raw"), 18);
-// 5x9
-pub const HOOK_WIDTH: u8 = 5;
-pub const HOOK_HEIGHT: u8 = 9;
-pub const PUDGE_HOOK: ImageRaw<BinaryColor> = ImageRaw::new(
-    include_bytes!("../../assets/PudgeHook.raw"),
-    HOOK_WIDTH as u32,
-);
-// 7x3
-pub const PUDGE_CLEAVER: ImageRaw<BinaryColor> =
-    ImageRaw::new(include_bytes!("../../assets/PudgeCleaver.raw"), 3);
-// 13x11
-pub const CREEP_WIDTH: u8 = 13;
-pub const CREEP_HEIGHT: u8 = 11;
-pub const CREEP: ImageRaw<BinaryColor> =
-    ImageRaw::new(include_bytes!("../../assets/RadiantCreep.raw"), 13);
-pub const DIRE_CREEP: ImageRaw<BinaryColor> =
-    ImageRaw::new(include_bytes!("../../assets/RadiantCreep.raw"), 13);
+// Generated by build.rs from the images in assets/
+include!(concat!(env!("OUT_DIR"), "/sprites.rs"));
+
+pub const PUDGE_WIDTH: u8 = PUDGE_BODY_WIDTH;
+pub const PUDGE_HEIGHT: u8 = PUDGE_BODY_HEIGHT;
+pub const HOOK_WIDTH: u8 = PUDGE_HOOK_WIDTH;
+pub const HOOK_HEIGHT: u8 = PUDGE_HOOK_HEIGHT;
 
-pub const SPLASH: ImageRaw<BinaryColor> =
-    ImageRaw::new(include_bytes!("../../assets/Splash.raw"), 128);
+// Animation sheets, frames are played in order and looped
+pub const PUDGE_IDLE_FRAMES: [ImageRaw<BinaryColor>; 1] = [PUDGE_BODY];
+pub const PUDGE_THROW_FRAMES: [ImageRaw<BinaryColor>; 1] = [PUDGE_THROW];
+pub const PUDGE_REEL_FRAMES: [ImageRaw<BinaryColor>; 2] = [PUDGE_BODY, PUDGE_REEL];
+pub const HOOK_FLYING_FRAMES: [ImageRaw<BinaryColor>; 1] = [PUDGE_HOOK];
+pub const HOOK_REELING_FRAMES: [ImageRaw<BinaryColor>; 1] = [PUDGE_HOOK_REELING];
+pub const CLEAVER_FRAMES: [ImageRaw<BinaryColor>; 1] = [PUDGE_CLEAVER];
+pub const CREEP_WALK_FRAMES: [ImageRaw<BinaryColor>; 2] = [CREEP, CREEP_STEP];
+pub const DIRE_CREEP_WALK_FRAMES: [ImageRaw<BinaryColor>; 2] = [DIRE_CREEP, CREEP_STEP];
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Sprite {
     Pudge,
+    PudgeThrowing,
+    PudgeReeling,
     Creep(Creep),
     Hook,
+    HookReeling,
     Cleaver,
 }
 
 impl Sprite {
-    pub fn get_image(&self) -> ImageRaw<'static, BinaryColor> {
+    pub const ALL: [Sprite; 8] = [
+        Sprite::Pudge,
+        Sprite::PudgeThrowing,
+        Sprite::PudgeReeling,
+        Sprite::Creep(Creep::Radiant),
+        Sprite::Creep(Creep::Dire),
+        Sprite::Hook,
+        Sprite::HookReeling,
+        Sprite::Cleaver,
+    ];
+
+    // Sprite pack files are named `<name>_<frame>.bmp`
+    pub fn name(&self) -> &'static str {
         match self {
-            Sprite::Pudge => PUDGE_BODY,
+            Sprite::Pudge => "pudge",
+            Sprite::PudgeThrowing => "pudge_throwing",
+            Sprite::PudgeReeling => "pudge_reeling",
+            Sprite::Creep(Creep::Radiant) => "creep_radiant",
+            Sprite::Creep(Creep::Dire) => "creep_dire",
+            Sprite::Hook => "hook",
+            Sprite::HookReeling => "hook_reeling",
+            Sprite::Cleaver => "cleaver",
+        }
+    }
+
+    pub fn get_frames(&self) -> &'static [ImageRaw<'static, BinaryColor>] {
+        #[cfg(not(target_os = "none"))]
+        if let Some(frames) = sprite_pack::frames(self) {
+            return frames;
+        }
+        self.builtin_frames()
+    }
+
+    pub fn builtin_frames(&self) -> &'static [ImageRaw<'static, BinaryColor>] {
+        match self {
+            Sprite::Pudge => &PUDGE_IDLE_FRAMES,
+            Sprite::PudgeThrowing => &PUDGE_THROW_FRAMES,
+            Sprite::PudgeReeling => &PUDGE_REEL_FRAMES,
             Sprite::Creep(creep_type) => match creep_type {
-                Creep::Dire => CREEP,
-                Creep::Radiant => CREEP,
+                Creep::Dire => &DIRE_CREEP_WALK_FRAMES,
+                Creep::Radiant => &CREEP_WALK_FRAMES,
             },
-            Sprite::Hook => PUDGE_HOOK,
-            Sprite::Cleaver => PUDGE_CLEAVER,
+            Sprite::Hook => &HOOK_FLYING_FRAMES,
+            Sprite::HookReeling => &HOOK_REELING_FRAMES,
+            Sprite::Cleaver => &CLEAVER_FRAMES,
+        }
+    }
+
+    pub fn for_hookable(hookable: &Hookable) -> Option<Sprite> {
+        match hookable {
+            Hookable::Creep(creep_type) => Some(Sprite::Creep(*creep_type)),
+            _ => None,
         }
     }
+
+    // Every frame of a sheet is expected to share the first frame's size,
+    // sprite packs can't change it so hitboxes stay the same
+    pub fn size(&self) -> (u8, u8) {
+        let size = self.builtin_frames()[0].size();
+        (size.width as u8, size.height as u8)
+    }
+
+    pub fn get_frame(&self, frame: usize) -> ImageRaw<'static, BinaryColor> {
+        let frames = self.get_frames();
+        frames[frame % frames.len()]
+    }
 }
@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{Cursor, Read};
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+use embedded_graphics::{
+    image::ImageRaw,
+    pixelcolor::{BinaryColor, Rgb888},
+    prelude::*,
+};
+use once_cell::sync::OnceCell;
+use tinybmp::Bmp;
+
+use super::resources::Sprite;
+
+// Installed once at startup, the firmware always uses the compiled-in sprites
+static SPRITE_PACK: OnceCell<SpritePack> = OnceCell::new();
+
+#[derive(Debug)]
+pub enum SpritePackError {
+    Io(std::io::Error),
+    Zip(zip::result::ZipError),
+    Bmp(String),
+    SizeMismatch(String),
+    Empty,
+    AlreadyInstalled,
+}
+
+impl fmt::Display for SpritePackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpritePackError::Io(err) => write!(f, "couldn't read sprite pack: {}", err),
+            SpritePackError::Zip(err) => write!(f, "couldn't unpack sprite pack: {}", err),
+            SpritePackError::Bmp(name) => write!(f, "{} is not a valid BMP", name),
+            SpritePackError::SizeMismatch(name) => {
+                write!(f, "{} doesn't match the built-in sprite size", name)
+            }
+            SpritePackError::Empty => write!(f, "no known sprites in the pack"),
+            SpritePackError::AlreadyInstalled => write!(f, "a sprite pack is already installed"),
+        }
+    }
+}
+
+impl From<std::io::Error> for SpritePackError {
+    fn from(err: std::io::Error) -> Self {
+        SpritePackError::Io(err)
+    }
+}
+
+impl From<zip::result::ZipError> for SpritePackError {
+    fn from(err: zip::result::ZipError) -> Self {
+        SpritePackError::Zip(err)
+    }
+}
+
+// Overrides whole animation sheets. A sheet is replaced when the pack has
+// `<sprite name>_0.bmp`, further frames are picked up while they're numbered
+// consecutively. Frames have to match the built-in size so hitboxes don't change.
+pub struct SpritePack {
+    sheets: Vec<(Sprite, &'static [ImageRaw<'static, BinaryColor>])>,
+}
+
+impl SpritePack {
+    pub fn from_files<I>(files: I) -> Result<Self, SpritePackError>
+    where
+        I: IntoIterator<Item = (String, Vec<u8>)>,
+    {
+        // Zips and directories may nest the images, only file names matter
+        let files: HashMap<String, Vec<u8>> = files
+            .into_iter()
+            .map(|(path, data)| (file_name(&path).to_owned(), data))
+            .collect();
+
+        let mut sheets = Vec::new();
+        for sprite in Sprite::ALL {
+            let mut frames = Vec::new();
+            loop {
+                let name = format!("{}_{}.bmp", sprite.name(), frames.len());
+                let Some(data) = files.get(&name) else {
+                    break;
+                };
+                let frame = load_frame(&name, data)?;
+                if frame.size() != sprite.builtin_frames()[0].size() {
+                    return Err(SpritePackError::SizeMismatch(name));
+                }
+                frames.push(frame);
+            }
+            if !frames.is_empty() {
+                // Lives for the rest of the program, same as the built-in sheets
+                let frames: &'static [ImageRaw<'static, BinaryColor>] =
+                    Box::leak(frames.into_boxed_slice());
+                sheets.push((sprite, frames));
+            }
+        }
+
+        if sheets.is_empty() {
+            return Err(SpritePackError::Empty);
+        }
+        Ok(SpritePack { sheets })
+    }
+
+    pub fn from_zip(data: &[u8]) -> Result<Self, SpritePackError> {
+        let mut archive = zip::ZipArchive::new(Cursor::new(data))?;
+        let mut files = Vec::new();
+        for idx in 0..archive.len() {
+            let mut file = archive.by_index(idx)?;
+            if file.is_dir() {
+                continue;
+            }
+            let mut data = Vec::new();
+            file.read_to_end(&mut data)?;
+            files.push((file.name().to_owned(), data));
+        }
+        Self::from_files(files)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_dir(path: &Path) -> Result<Self, SpritePackError> {
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(path)? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                files.push((name, std::fs::read(entry.path())?));
+            }
+        }
+        Self::from_files(files)
+    }
+
+    // Directory or zip archive
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: &Path) -> Result<Self, SpritePackError> {
+        if path.is_dir() {
+            return Self::from_dir(path);
+        }
+        Self::from_zip(&std::fs::read(path)?)
+    }
+
+    pub fn sheet_count(&self) -> usize {
+        self.sheets.len()
+    }
+
+    pub fn install(self) -> Result<(), SpritePackError> {
+        SPRITE_PACK
+            .set(self)
+            .map_err(|_| SpritePackError::AlreadyInstalled)
+    }
+}
+
+pub fn frames(sprite: &Sprite) -> Option<&'static [ImageRaw<'static, BinaryColor>]> {
+    SPRITE_PACK
+        .get()?
+        .sheets
+        .iter()
+        .find(|(pack_sprite, _)| pack_sprite == sprite)
+        .map(|(_, frames)| *frames)
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+// Same 1-bit packing build.rs uses for the built-in sprites
+fn load_frame(name: &str, data: &[u8]) -> Result<ImageRaw<'static, BinaryColor>, SpritePackError> {
+    let bmp = Bmp::<Rgb888>::from_slice(data)
+        .map_err(|err| SpritePackError::Bmp(format!("{} ({:?})", name, err)))?;
+    let Size { width, height } = bmp.size();
+    let row_bytes = width.div_ceil(8) as usize;
+    let mut packed = vec![0u8; row_bytes * height as usize];
+    for Pixel(point, color) in bmp.pixels() {
+        let luma = (color.r() as u32 + color.g() as u32 + color.b() as u32) / 3;
+        if luma > 127 {
+            let x = point.x as usize;
+            packed[point.y as usize * row_bytes + x / 8] |= 0x80 >> (x % 8);
+        }
+    }
+    Ok(ImageRaw::new(Box::leak(packed.into_boxed_slice()), width))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::resources::CREEP_STEP;
+
+    // Uncompressed 24-bit BMP, `lit` picks the white pixels
+    fn bmp(width: u32, height: u32, lit: impl Fn(u32, u32) -> bool) -> Vec<u8> {
+        let row_len = (width * 3).div_ceil(4) * 4;
+        let image_len = row_len * height;
+        let mut data = Vec::new();
+        data.extend_from_slice(b"BM");
+        data.extend_from_slice(&(54 + image_len).to_le_bytes());
+        data.extend_from_slice(&[0; 4]);
+        data.extend_from_slice(&54u32.to_le_bytes());
+        data.extend_from_slice(&40u32.to_le_bytes());
+        data.extend_from_slice(&width.to_le_bytes());
+        data.extend_from_slice(&height.to_le_bytes());
+        data.extend_from_slice(&1u16.to_le_bytes());
+        data.extend_from_slice(&24u16.to_le_bytes());
+        data.extend_from_slice(&[0; 4]);
+        data.extend_from_slice(&image_len.to_le_bytes());
+        data.extend_from_slice(&[0; 16]);
+        // Rows are stored bottom-up
+        for y in (0..height).rev() {
+            let mut row = Vec::new();
+            for x in 0..width {
+                let value = if lit(x, y) { 0xff } else { 0 };
+                row.extend_from_slice(&[value; 3]);
+            }
+            row.resize(row_len as usize, 0);
+            data.extend_from_slice(&row);
+        }
+        data
+    }
+
+    fn sprite_bmp(sprite: Sprite, lit: impl Fn(u32, u32) -> bool) -> Vec<u8> {
+        let (width, height) = sprite.size();
+        bmp(width as u32, height as u32, lit)
+    }
+
+    #[test]
+    fn test_sheet_override_takes_consecutive_frames() {
+        let sprite = Sprite::PudgeReeling;
+        let pack = SpritePack::from_files([
+            (
+                "pack/pudge_reeling_0.bmp".to_owned(),
+                sprite_bmp(sprite, |_, _| true),
+            ),
+            (
+                "pack/pudge_reeling_1.bmp".to_owned(),
+                sprite_bmp(sprite, |x, _| x == 0),
+            ),
+            // Not consecutive, ignored
+            (
+                "pack/pudge_reeling_3.bmp".to_owned(),
+                sprite_bmp(sprite, |_, _| false),
+            ),
+            ("pack/unknown_0.bmp".to_owned(), bmp(3, 3, |_, _| true)),
+        ])
+        .unwrap();
+
+        assert_eq!(pack.sheet_count(), 1);
+        let (pack_sprite, frames) = pack.sheets[0];
+        assert_eq!(pack_sprite, sprite);
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].size(), sprite.builtin_frames()[0].size());
+        assert_ne!(frames[0], frames[1]);
+    }
+
+    #[test]
+    fn test_frames_have_to_match_the_builtin_size() {
+        let err = SpritePack::from_files([("pudge_0.bmp".to_owned(), bmp(4, 4, |_, _| true))]);
+        assert!(matches!(err, Err(SpritePackError::SizeMismatch(name)) if name == "pudge_0.bmp"));
+    }
+
+    #[test]
+    fn test_pack_without_known_sprites_is_empty() {
+        let err = SpritePack::from_files([
+            ("readme.txt".to_owned(), b"hello".to_vec()),
+            (
+                "pudge_1.bmp".to_owned(),
+                sprite_bmp(Sprite::Pudge, |_, _| true),
+            ),
+        ]);
+        assert!(matches!(err, Err(SpritePackError::Empty)));
+    }
+
+    #[test]
+    fn test_frames_are_packed_like_the_builtin_sprites() {
+        // 13 wide, every row spills into a second byte
+        let frame =
+            load_frame("step", include_bytes!("../../assets/RadiantCreepStep.bmp")).unwrap();
+        assert_eq!(frame, CREEP_STEP);
+
+        let frame = load_frame("edges", &bmp(13, 2, |x, y| x == 0 || x == 12 - y)).unwrap();
+        let expected: ImageRaw<BinaryColor> =
+            ImageRaw::new(&[0b1000_0000, 0b0000_1000, 0b1000_0000, 0b0001_0000], 13);
+        assert_eq!(frame, expected);
+    }
+}
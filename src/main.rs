@@ -9,6 +9,10 @@ pub mod graphics;
 mod embed;
 #[cfg(not(any(target_os = "none", target_os = "unknown")))]
 mod native;
+#[cfg(not(target_os = "none"))]
+mod net;
+#[cfg(not(any(target_os = "none", target_os = "unknown")))]
+mod script;
 #[cfg(target_arch = "wasm32")]
 mod wasm;
 
@@ -17,11 +21,6 @@ use bsp::entry;
 #[cfg(target_os = "none")]
 use rp_pico as bsp;
 
-use embedded_graphics::image::Image;
-use embedded_graphics::pixelcolor::BinaryColor;
-
-type SpriteImage = Image<'static, embedded_graphics::image::ImageRaw<'static, BinaryColor>>;
-
 #[cfg(not(any(target_os = "none", target_os = "unknown")))]
 fn main() -> Result<(), core::convert::Infallible> {
     use native::native_main;
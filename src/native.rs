@@ -3,8 +3,10 @@ use graphics::display::clear_display;
 use game::Instant;
 
 use crate::controls::native::window_controls;
+use crate::net::{native::NativeLink, Session};
+use crate::script::ModeScript;
 use crate::{
-    controls::ControlEnum,
+    controls::{ControlEnum, Player},
     graphics::{display, resources::SPLASH},
 };
 
@@ -14,7 +16,7 @@ use std::thread::sleep;
 
 use graphics::display::DisplayEnum;
 
-use game::Game;
+use game::{parse_waves, Bot, Game, GameEvent, Mode, Run, Scene};
 
 use crate::{game, graphics};
 
@@ -23,12 +25,19 @@ use graphics::image::draw_image;
 use embedded_graphics::{image::Image, prelude::*};
 
 use graphics::display::get_display;
+use graphics::sprite_pack::SpritePack;
 use once_cell::sync::OnceCell;
+use std::path::Path;
 use std::time::SystemTime;
 
 static START_TIME: OnceCell<SystemTime> = OnceCell::new();
 
 pub(crate) fn native_main() -> Result<(), core::convert::Infallible> {
+    if let Some(runs) = arg_value("--headless") {
+        run_headless(runs.parse().unwrap_or(10));
+        return Ok(());
+    }
+
     let mut window = if cfg!(not(target_os = "none")) {
         println!("Creating window");
         Some(display::simulator::create_window())
@@ -36,6 +45,8 @@ pub(crate) fn native_main() -> Result<(), core::convert::Infallible> {
         None
     };
 
+    load_sprite_pack();
+
     println!("Display init");
     let mut display = get_display();
 
@@ -44,7 +55,12 @@ pub(crate) fn native_main() -> Result<(), core::convert::Infallible> {
 
     println!("Game init");
     let mut game = Game::default();
+    load_waves(&mut game);
     game.init();
+    let mut script = load_mode(&mut game);
+    let mut session = connect_relay();
+    // `--log-events` prints what happens in the game, for debugging
+    let log_events = std::env::args().any(|arg| arg == "--log-events");
 
     #[cfg(not(target_os = "none"))]
     if let DisplayEnum::Simulator(ref mut disp) = display {
@@ -59,7 +75,14 @@ pub(crate) fn native_main() -> Result<(), core::convert::Infallible> {
 
     sleep(Duration::from_secs(1));
     'running: loop {
-        let err = main_loop(&mut window, &mut display, &mut game);
+        let err = main_loop(
+            &mut window,
+            &mut display,
+            &mut game,
+            &mut session,
+            &mut script,
+            log_events,
+        );
         if err.is_err() {
             break 'running;
         }
@@ -67,12 +90,159 @@ pub(crate) fn native_main() -> Result<(), core::convert::Infallible> {
     Ok(())
 }
 
+fn arg_value(name: &str) -> Option<String> {
+    let mut args = std::env::args().skip_while(|arg| arg != name);
+    args.next()?;
+    args.next()
+}
+
+// `--sprites <dir or zip>` swaps the built-in sprites for a custom pack
+fn load_sprite_pack() {
+    let Some(path) = arg_value("--sprites") else {
+        return;
+    };
+    match SpritePack::load(Path::new(&path)).and_then(|pack| {
+        let sheets = pack.sheet_count();
+        pack.install().map(|_| sheets)
+    }) {
+        Ok(sheets) => println!("Loaded {} sprite sheets from {}", sheets, path),
+        Err(err) => println!("Using built-in sprites, {}", err),
+    }
+}
+
+// `--connect <host:port>` plays versus against whoever else joins that relay
+fn connect_relay() -> Option<Session<NativeLink>> {
+    let addr = arg_value("--connect")?;
+    match NativeLink::connect(&addr) {
+        Ok(link) => {
+            println!("Connected to {}, waiting for a second player", addr);
+            Some(Session::new(link, game::TICK))
+        }
+        Err(err) => {
+            println!("Playing offline, couldn't reach {}: {}", addr, err);
+            None
+        }
+    }
+}
+
+// Game time a headless run may take before it's called off
+const HEADLESS_RUN_LIMIT: Duration = Duration::from_secs(60 * 60);
+
+// `--headless <runs>` lets the bot play runs back to back on a simulated
+// clock without a window and prints how each went, for balancing.
+// `--headless-mode <name>` picks the built-in mode, classic by default
+fn run_headless(runs: u32) {
+    let mut game = Game::default();
+    load_waves(&mut game);
+    game.init();
+    let mut script = load_mode(&mut game);
+    let mode = match arg_value("--headless-mode") {
+        Some(name) => match Mode::from_name(&name) {
+            Some(mode) => mode,
+            None => {
+                println!("Unknown mode {}, playing classic", name);
+                Mode::Classic
+            }
+        },
+        None => Mode::Classic,
+    };
+    let bot = Bot::new(Player::One);
+    let tick = game::TICK.to_micros();
+
+    let mut clock = 0;
+    let mut run_start = 0;
+    let (mut hooks, mut misses) = (0, 0);
+    let (mut finished, mut total_score, mut total_stage) = (0, 0, 0);
+    while finished < runs {
+        // Also catches a bot that never gets a run going
+        if clock - run_start > HEADLESS_RUN_LIMIT.as_micros() as u64 {
+            println!(
+                "Run {} still going after {:?}, stopping",
+                finished + 1,
+                HEADLESS_RUN_LIMIT
+            );
+            break;
+        }
+        if !game.scene().is_playing() {
+            game.start_run(mode);
+            run_start = clock;
+            (hooks, misses) = (0, 0);
+        }
+        game.control(Player::One, bot.control(&game));
+        let now = Instant::from_ticks(clock);
+        if let Err(err) = game.process(now) {
+            println!("Skipped tick, {}", err);
+        }
+        for event in drain_events(&mut game, &mut script, now) {
+            match event {
+                GameEvent::Scored(_, points) if points > 0 => hooks += 1,
+                GameEvent::Missed(_) => misses += 1,
+                GameEvent::SceneChanged(_, Scene::GameOver(Run::Solo(_, score, _, stage))) => {
+                    finished += 1;
+                    total_score += score as i64;
+                    total_stage += stage as u32;
+                    let seconds = (clock - run_start) / 1_000_000;
+                    println!(
+                        "Run {}: score {}, stage {}, {} hooks, {} misses, {}s",
+                        finished, score, stage, hooks, misses, seconds
+                    );
+                }
+                _ => {}
+            }
+        }
+        clock += tick;
+    }
+    if finished > 0 {
+        println!(
+            "Average over {} runs: score {}, stage {:.1}",
+            finished,
+            total_score / finished as i64,
+            total_stage as f32 / finished as f32
+        );
+    }
+}
+
+// `--mode <file>` plays a custom mode written in Rhai, see assets/modes
+fn load_mode(game: &mut Game) -> Option<ModeScript> {
+    let path = arg_value("--mode")?;
+    match ModeScript::load(Path::new(&path), game) {
+        Ok(script) => {
+            println!("Playing mode {}", path);
+            Some(script)
+        }
+        Err(err) => {
+            println!("Playing without {}, {}", path, err);
+            None
+        }
+    }
+}
+
+// `--waves <file>` replaces the default spawn waves, see assets/waves.txt
+fn load_waves(game: &mut Game) {
+    let Some(path) = arg_value("--waves") else {
+        return;
+    };
+    match std::fs::read_to_string(&path)
+        .map_err(|err| err.to_string())
+        .and_then(|text| parse_waves(&text).map_err(|err| err.to_string()))
+        .and_then(|waves| {
+            game.set_waves(&waves).map_err(|err| err.to_string())?;
+            Ok(waves.len())
+        }) {
+        Ok(count) => println!("Loaded {} waves from {}", count, path),
+        Err(err) => println!("Using default waves, {}: {}", path, err),
+    }
+}
+
 pub(crate) fn main_loop(
     window: &mut Option<embedded_graphics_simulator::Window>,
     display: &mut DisplayEnum,
     game: &mut Game,
+    session: &mut Option<Session<NativeLink>>,
+    script: &mut Option<ModeScript>,
+    log_events: bool,
 ) -> Result<(), ()> {
-    let mut controls: Option<ControlEnum> = None;
+    let mut controls: [Option<ControlEnum>; 2] = [None; 2];
 
     let ticks: u64 = if cfg!(not(target_os = "none")) {
         let delta = START_TIME.get().unwrap().elapsed().expect("NO TIIIIME");
@@ -95,9 +265,71 @@ pub(crate) fn main_loop(
         }
     }
 
-    if let Some(ctrl) = controls {
-        game.control(ctrl)
+    let mut handle_events = |game: &mut Game| {
+        if log_events && game.dropped_events() > 0 {
+            println!("Dropped {} game events", game.dropped_events());
+        }
+        for event in drain_events(game, script, clock) {
+            if log_events {
+                log_event(event);
+            }
+        }
+    };
+
+    if let Some(online) = session {
+        // Either keyboard half steers the local Pudge
+        if let Some(ctrl) = controls[0].or(controls[1]) {
+            online.control(game, ctrl);
+        }
+        let local = online.local_player();
+        if let Err(err) = online.update(game, clock, &mut handle_events) {
+            println!(
+                "Online match over after {} ticks, {}",
+                online.tick().unwrap_or_default(),
+                err
+            );
+            *session = None;
+        } else if local.is_none() && online.local_player().is_some() {
+            println!("Match started as {:?}", online.local_player().unwrap());
+        }
+    } else {
+        for (player, ctrl) in [Player::One, Player::Two].into_iter().zip(controls) {
+            if let Some(ctrl) = ctrl {
+                game.control(player, ctrl)
+            }
+        }
+        if let Err(err) = game.process(clock) {
+            println!("Skipped tick, {}", err);
+        }
     }
-    game.process(clock);
+    handle_events(game);
     Ok(())
 }
+
+// Events since the last drain, after the mode script saw them. Runs the script
+// ended come after.
+fn drain_events(game: &mut Game, script: &mut Option<ModeScript>, now: Instant) -> Vec<GameEvent> {
+    let mut events: Vec<GameEvent> = game.drain_events().collect();
+    if let Some(mode) = script {
+        if let Err(err) = mode.update(game, now, &events) {
+            println!("Mode script stopped, {}", err);
+            *script = None;
+        }
+        events.extend(game.drain_events());
+    }
+    events
+}
+
+fn log_event(event: GameEvent) {
+    match event {
+        GameEvent::Spawned(id, kind) => println!("Spawned {:?} #{}", kind, id),
+        GameEvent::Hooked(id, kind) => println!("Hooked {:?} #{}", kind, id),
+        GameEvent::Reeled(kind) => println!("Reeled {:?}", kind),
+        GameEvent::Missed(player) => println!("{:?} missed", player),
+        GameEvent::Escaped(id, kind) => println!("Escaped {:?} #{}", kind, id),
+        GameEvent::Damaged(player, hp) => println!("{:?} damaged, {} HP left", player, hp),
+        GameEvent::Scored(player, points) => println!("{:?} scored {}", player, points),
+        GameEvent::StageReached(stage) => println!("Stage {}", stage),
+        GameEvent::SceneChanged(from, to) => println!("{} -> {}", from.to_str(), to.to_str()),
+    }
+}
@@ -0,0 +1,221 @@
+use core::fmt;
+use std::collections::VecDeque;
+
+use super::Message;
+use crate::controls::{ControlEnum, Player};
+use crate::game::{Game, GameError, Instant, TICK};
+
+// Ticks between a control being read and played, the time it has to
+// reach the other client before it's waited for
+pub const INPUT_DELAY: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LockstepError {
+    // Both clients play the next tick only once it has both inputs
+    NotReady,
+    OutOfOrder { expected: u32, got: u32 },
+    UnknownControl(u8),
+    Game(GameError),
+}
+
+impl fmt::Display for LockstepError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LockstepError::NotReady => write!(f, "waiting for the other player"),
+            LockstepError::OutOfOrder { expected, got } => {
+                write!(f, "input for tick {} while waiting for {}", got, expected)
+            }
+            LockstepError::UnknownControl(code) => write!(f, "unknown control {}", code),
+            LockstepError::Game(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+// Debug toggles never leave the client
+fn control_code(control: ControlEnum) -> u8 {
+    match control {
+        ControlEnum::Left => 1,
+        ControlEnum::Right => 2,
+        ControlEnum::Hook => 3,
+        ControlEnum::None | ControlEnum::Debug | ControlEnum::Hitboxes | ControlEnum::Pause => 0,
+    }
+}
+
+fn control_from_code(code: u8) -> Result<ControlEnum, LockstepError> {
+    match code {
+        0 => Ok(ControlEnum::None),
+        1 => Ok(ControlEnum::Left),
+        2 => Ok(ControlEnum::Right),
+        3 => Ok(ControlEnum::Hook),
+        _ => Err(LockstepError::UnknownControl(code)),
+    }
+}
+
+// Runs the game on a clock of its own, one `TICK` per step, so both
+// clients play the same ticks with the same inputs and stay in sync
+pub struct Lockstep {
+    local: Player,
+    tick: u32,
+    // Controls from `tick` on, indexed by `Player::index`
+    inputs: [VecDeque<ControlEnum>; 2],
+}
+
+impl Lockstep {
+    pub fn start(game: &mut Game, local: Player, seed: u64) -> Self {
+        game.set_seed(seed);
+        game.init();
+        // Only sets the clock, the first tick is played by `step`
+        let _ = game.process(Instant::from_ticks(0));
+        game.start_versus();
+        let idle: VecDeque<ControlEnum> = (0..INPUT_DELAY).map(|_| ControlEnum::None).collect();
+        Lockstep {
+            local,
+            tick: 0,
+            inputs: [idle.clone(), idle],
+        }
+    }
+
+    pub fn local_player(&self) -> Player {
+        self.local
+    }
+
+    pub fn tick(&self) -> u32 {
+        self.tick
+    }
+
+    pub fn ready(&self) -> bool {
+        self.inputs.iter().all(|inputs| !inputs.is_empty())
+    }
+
+    // Inputs from the other client, in tick order
+    pub fn receive(&mut self, message: Message) -> Result<(), LockstepError> {
+        let Message::Input { tick, control } = message else {
+            return Ok(());
+        };
+        let remote = &mut self.inputs[self.local.other().index()];
+        let expected = self.tick + remote.len() as u32;
+        if tick != expected {
+            return Err(LockstepError::OutOfOrder {
+                expected,
+                got: tick,
+            });
+        }
+        remote.push_back(control_from_code(control)?);
+        Ok(())
+    }
+
+    // Plays the next tick and schedules `control` `INPUT_DELAY` ticks
+    // later. The returned message has to reach the other client.
+    pub fn step(
+        &mut self,
+        game: &mut Game,
+        control: ControlEnum,
+    ) -> Result<Message, LockstepError> {
+        if !self.ready() {
+            return Err(LockstepError::NotReady);
+        }
+        for player in [Player::One, Player::Two] {
+            let input = self.inputs[player.index()].pop_front();
+            if let Some(input) = input.filter(ControlEnum::is_some) {
+                game.control(player, input);
+            }
+        }
+        self.tick += 1;
+        let clock = Instant::from_ticks(self.tick as u64 * TICK.to_micros());
+        game.process(clock).map_err(LockstepError::Game)?;
+
+        let code = control_code(control);
+        self.inputs[self.local.index()].push_back(control_from_code(code)?);
+        Ok(Message::Input {
+            tick: self.tick - 1 + INPUT_DELAY,
+            control: code,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{GameEvent, Scene};
+
+    // Made up inputs that keep both Pudges moving and hooking
+    fn script(player: Player, tick: u32) -> ControlEnum {
+        match (tick + player.index() as u32 * 7) % 23 {
+            0..=5 => ControlEnum::Left,
+            9..=14 => ControlEnum::Right,
+            18 => ControlEnum::Hook,
+            _ => ControlEnum::None,
+        }
+    }
+
+    #[test]
+    fn test_peers_stay_in_sync() {
+        let mut games = [Game::default(), Game::default()];
+        let mut peers = [
+            Lockstep::start(&mut games[0], Player::One, 42),
+            Lockstep::start(&mut games[1], Player::Two, 42),
+        ];
+        // Messages in flight to each peer, and every tick's state and events
+        let mut wires: [VecDeque<Message>; 2] = Default::default();
+        let mut history: [Vec<(Scene, Vec<GameEvent>)>; 2] = Default::default();
+
+        for frame in 0..3000u32 {
+            for side in 0..2 {
+                // Uneven delivery, the second peer lags every few frames
+                let lagging = side == 1 && frame % 5 == 0;
+                if lagging || peers[side].tick() >= 2000 {
+                    continue;
+                }
+                while let Some(message) = wires[side].pop_front() {
+                    peers[side].receive(message).unwrap();
+                }
+                if !peers[side].ready() {
+                    continue;
+                }
+                let control = script(peers[side].local_player(), peers[side].tick());
+                let message = peers[side].step(&mut games[side], control).unwrap();
+                wires[1 - side].push_back(message);
+                let events = games[side].drain_events().collect();
+                history[side].push((games[side].scene(), events));
+            }
+        }
+
+        assert_eq!(history[0].len(), 2000);
+        assert!(history[0] == history[1]);
+        let scored = history[0]
+            .iter()
+            .flat_map(|(_, events)| events)
+            .any(|event| matches!(event, GameEvent::Scored(_, points) if *points > 0));
+        assert!(scored, "nothing got hooked, the script needs tuning");
+    }
+
+    #[test]
+    fn test_waits_for_the_other_input() {
+        let mut game = Game::default();
+        let mut lockstep = Lockstep::start(&mut game, Player::One, 1);
+        for _ in 0..INPUT_DELAY {
+            assert!(lockstep.step(&mut game, ControlEnum::Hook).is_ok());
+        }
+        assert_eq!(
+            lockstep.step(&mut game, ControlEnum::None),
+            Err(LockstepError::NotReady)
+        );
+        assert_eq!(
+            lockstep.receive(Message::Input {
+                tick: INPUT_DELAY + 1,
+                control: 0,
+            }),
+            Err(LockstepError::OutOfOrder {
+                expected: INPUT_DELAY,
+                got: INPUT_DELAY + 1,
+            })
+        );
+        assert!(lockstep
+            .receive(Message::Input {
+                tick: INPUT_DELAY,
+                control: 3,
+            })
+            .is_ok());
+        assert!(lockstep.step(&mut game, ControlEnum::None).is_ok());
+    }
+}
@@ -0,0 +1,148 @@
+// Online versus through the relay in `relay/`. Both clients run the whole
+// game and only trade inputs, see `lockstep`.
+
+pub mod lockstep;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod native;
+#[cfg(target_arch = "wasm32")]
+pub mod wasm;
+
+use core::fmt;
+
+use fugit::MicrosDurationU64;
+pub use poodg_relay::protocol::{Message, ProtocolError};
+
+use crate::controls::{ControlEnum, Player};
+use crate::game::{Game, Instant};
+use lockstep::{Lockstep, LockstepError};
+
+// Tick rates and speeds differ between native and WASM, so only
+// clients of the same build get paired
+#[cfg(target_arch = "wasm32")]
+pub const BUILD: u8 = 1;
+#[cfg(not(target_arch = "wasm32"))]
+pub const BUILD: u8 = 0;
+
+// Ticks played in one `update` at most when catching up
+const MAX_CATCH_UP: u32 = 4;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NetError {
+    Disconnected,
+    Rejected,
+    PeerLeft,
+    Protocol(ProtocolError),
+    Lockstep(LockstepError),
+}
+
+impl fmt::Display for NetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetError::Disconnected => write!(f, "lost the relay"),
+            NetError::Rejected => write!(f, "relay refused the connection"),
+            NetError::PeerLeft => write!(f, "the other player left"),
+            NetError::Protocol(err) => write!(f, "{}", err),
+            NetError::Lockstep(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+// A connection to the relay. Sends `Hello` on its own once connected.
+pub trait RelayLink {
+    fn send(&mut self, message: &Message) -> Result<(), NetError>;
+    // Next message that arrived, if any
+    fn poll(&mut self) -> Result<Option<Message>, NetError>;
+}
+
+pub struct Session<L: RelayLink> {
+    link: L,
+    // Wall time between ticks, the platform's frame pace
+    pace: MicrosDurationU64,
+    lockstep: Option<Lockstep>,
+    started_at: Option<Instant>,
+    control: ControlEnum,
+}
+
+impl<L: RelayLink> Session<L> {
+    pub fn new(link: L, pace: MicrosDurationU64) -> Self {
+        Session {
+            link,
+            pace,
+            lockstep: None,
+            started_at: None,
+            control: ControlEnum::None,
+        }
+    }
+
+    pub fn local_player(&self) -> Option<Player> {
+        self.lockstep.as_ref().map(Lockstep::local_player)
+    }
+
+    // Ticks played since the match started
+    pub fn tick(&self) -> Option<u32> {
+        self.lockstep.as_ref().map(Lockstep::tick)
+    }
+
+    // The latest control before a tick is the one sent for it.
+    // Debug toggles stay local.
+    pub fn control(&mut self, game: &mut Game, control: ControlEnum) {
+        match control {
+            ControlEnum::Debug | ControlEnum::Hitboxes => game.control(Player::One, control),
+            _ => self.control = control,
+        }
+    }
+
+    // Takes in what the relay sent and plays every tick that's due and has
+    // both inputs. The game is only driven from here while the session lasts.
+    // `after_tick` runs after each of them, catching up can play several and
+    // their events would pile up otherwise.
+    pub fn update<F: FnMut(&mut Game)>(
+        &mut self,
+        game: &mut Game,
+        now: Instant,
+        mut after_tick: F,
+    ) -> Result<(), NetError> {
+        while let Some(message) = self.link.poll()? {
+            match message {
+                Message::Start { player, seed } => {
+                    let local = if player == 0 {
+                        Player::One
+                    } else {
+                        Player::Two
+                    };
+                    self.lockstep = Some(Lockstep::start(game, local, seed));
+                    self.started_at = Some(now);
+                }
+                Message::Input { .. } => {
+                    if let Some(ref mut lockstep) = self.lockstep {
+                        lockstep.receive(message).map_err(NetError::Lockstep)?;
+                    }
+                }
+                Message::PeerLeft => return Err(NetError::PeerLeft),
+                Message::Rejected => return Err(NetError::Rejected),
+                Message::Hello { .. } => {}
+            }
+        }
+
+        let (Some(lockstep), Some(started_at)) = (self.lockstep.as_mut(), self.started_at) else {
+            return Ok(());
+        };
+        let elapsed = now
+            .checked_duration_since(started_at)
+            .map(|elapsed| elapsed.to_micros())
+            .unwrap_or_default();
+        let due = elapsed / self.pace.to_micros().max(1);
+        for _ in 0..MAX_CATCH_UP {
+            if lockstep.tick() as u64 >= due || !lockstep.ready() {
+                break;
+            }
+            let message = lockstep
+                .step(game, self.control)
+                .map_err(NetError::Lockstep)?;
+            self.control = ControlEnum::None;
+            self.link.send(&message)?;
+            after_tick(game);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,127 @@
+use std::io;
+use std::net::TcpStream;
+
+use poodg_relay::protocol::PROTOCOL_VERSION;
+use tungstenite::{Message as Frame, WebSocket};
+
+use super::{Message, NetError, RelayLink, BUILD};
+
+// Non-blocking once connected so the game loop never waits
+pub struct NativeLink {
+    socket: WebSocket<TcpStream>,
+}
+
+impl NativeLink {
+    // `addr` is `host:port` of a running `poodg-relay`
+    pub fn connect(addr: &str) -> io::Result<NativeLink> {
+        let stream = TcpStream::connect(addr)?;
+        stream.set_nodelay(true)?;
+        let (socket, _) = tungstenite::client(format!("ws://{}/", addr), stream)
+            .map_err(|_| io::Error::from(io::ErrorKind::ConnectionRefused))?;
+        socket.get_ref().set_nonblocking(true)?;
+
+        let mut link = NativeLink { socket };
+        link.send(&Message::Hello {
+            version: PROTOCOL_VERSION,
+            build: BUILD,
+        })
+        .map_err(|_| io::Error::from(io::ErrorKind::ConnectionAborted))?;
+        Ok(link)
+    }
+}
+
+fn would_block(err: &tungstenite::Error) -> bool {
+    matches!(err, tungstenite::Error::Io(err) if err.kind() == io::ErrorKind::WouldBlock)
+}
+
+impl RelayLink for NativeLink {
+    fn send(&mut self, message: &Message) -> Result<(), NetError> {
+        match self.socket.send(Frame::Binary(message.encode())) {
+            // Queued, goes out with the next send or poll
+            Err(err) if would_block(&err) => Ok(()),
+            result => result.map_err(|_| NetError::Disconnected),
+        }
+    }
+
+    fn poll(&mut self) -> Result<Option<Message>, NetError> {
+        loop {
+            match self.socket.read() {
+                Ok(Frame::Binary(payload)) => {
+                    return Message::decode(&payload)
+                        .map(Some)
+                        .map_err(NetError::Protocol)
+                }
+                Ok(Frame::Close(_)) => return Err(NetError::Disconnected),
+                // Pings are answered by tungstenite
+                Ok(_) => {}
+                Err(err) if would_block(&err) => return Ok(None),
+                Err(_) => return Err(NetError::Disconnected),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+    use std::thread;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::controls::{ControlEnum, Player};
+    use crate::game::{Game, Instant, TICK};
+    use crate::net::Session;
+
+    #[test]
+    fn test_two_clients_play_through_the_relay() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || poodg_relay::relay::serve(listener));
+
+        let mut games = [Game::default(), Game::default()];
+        let mut sessions = [
+            Session::new(NativeLink::connect(&addr).unwrap(), TICK),
+            Session::new(NativeLink::connect(&addr).unwrap(), TICK),
+        ];
+        let mut started_at = [None; 2];
+        let mut played = 0;
+        for frame in 1..5000u64 {
+            let now = Instant::from_ticks(frame * TICK.to_micros());
+            for ((session, game), started_at) in sessions
+                .iter_mut()
+                .zip(games.iter_mut())
+                .zip(started_at.iter_mut())
+            {
+                // No more than 300 ticks are ever due, the leader waits there
+                // and whoever catches up can't run past it
+                let now =
+                    started_at.map_or(now, |started_at: Instant| now.min(started_at + TICK * 300));
+                if frame % 50 == 0 {
+                    session.control(game, ControlEnum::Hook);
+                }
+                session
+                    .update(game, now, |game| {
+                        played += 1;
+                        let _ = game.drain_events();
+                    })
+                    .unwrap();
+                if started_at.is_none() && session.tick().is_some() {
+                    *started_at = Some(now);
+                }
+            }
+            if sessions.iter().all(|session| session.tick() == Some(300)) {
+                break;
+            }
+            thread::sleep(Duration::from_micros(200));
+        }
+
+        let players = sessions.each_ref().map(|session| session.local_player());
+        assert!(players.contains(&Some(Player::One)));
+        assert!(players.contains(&Some(Player::Two)));
+        assert_eq!(sessions[0].tick(), Some(300));
+        assert_eq!(sessions[1].tick(), Some(300));
+        assert_eq!(played, 600);
+        assert_eq!(games[0].scene(), games[1].scene());
+        assert!(games[0].scene().is_playing());
+    }
+}
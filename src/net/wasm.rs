@@ -0,0 +1,75 @@
+use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+
+use poodg_relay::protocol::PROTOCOL_VERSION;
+use wasm_bindgen::prelude::*;
+use web_sys::{BinaryType, MessageEvent, WebSocket};
+
+use super::{Message, NetError, RelayLink, BUILD};
+
+type Inbox = Rc<RefCell<VecDeque<Result<Message, NetError>>>>;
+
+// The browser's WebSocket, its callbacks fill `inbox` between frames
+pub struct WasmLink {
+    socket: WebSocket,
+    inbox: Inbox,
+}
+
+impl WasmLink {
+    // `url` like `ws://127.0.0.1:9001`
+    pub fn connect(url: &str) -> Result<WasmLink, JsValue> {
+        let socket = WebSocket::new(url)?;
+        socket.set_binary_type(BinaryType::Arraybuffer);
+        let inbox: Inbox = Rc::new(RefCell::new(VecDeque::new()));
+
+        let on_open = {
+            let socket = socket.clone();
+            Closure::wrap(Box::new(move || {
+                let hello = Message::Hello {
+                    version: PROTOCOL_VERSION,
+                    build: BUILD,
+                };
+                let _ = socket.send_with_u8_array(&hello.encode());
+            }) as Box<dyn FnMut()>)
+        };
+        socket.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+        on_open.forget();
+
+        let on_message = {
+            let inbox = inbox.clone();
+            Closure::wrap(Box::new(move |event: MessageEvent| {
+                let Ok(buffer) = event.data().dyn_into::<js_sys::ArrayBuffer>() else {
+                    return;
+                };
+                let bytes = js_sys::Uint8Array::new(&buffer).to_vec();
+                let message = Message::decode(&bytes).map_err(NetError::Protocol);
+                inbox.borrow_mut().push_back(message);
+            }) as Box<dyn FnMut(_)>)
+        };
+        socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        on_message.forget();
+
+        // Errors are always followed by a close
+        let on_close = {
+            let inbox = inbox.clone();
+            Closure::wrap(Box::new(move || {
+                inbox.borrow_mut().push_back(Err(NetError::Disconnected));
+            }) as Box<dyn FnMut()>)
+        };
+        socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+        on_close.forget();
+
+        Ok(WasmLink { socket, inbox })
+    }
+}
+
+impl RelayLink for WasmLink {
+    fn send(&mut self, message: &Message) -> Result<(), NetError> {
+        self.socket
+            .send_with_u8_array(&message.encode())
+            .map_err(|_| NetError::Disconnected)
+    }
+
+    fn poll(&mut self) -> Result<Option<Message>, NetError> {
+        self.inbox.borrow_mut().pop_front().transpose()
+    }
+}
@@ -0,0 +1,470 @@
+// Custom game modes in Rhai, native only. Top level code runs once when the
+// script is loaded and sets the mode up, functions named after the callbacks
+// below are called as single player runs play out. See the README for the
+// whole API.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::path::Path;
+use std::rc::Rc;
+
+use rhai::{CallFnOptions, Dynamic, Engine, EvalAltResult, FuncArgs, Map, Scope, AST, FLOAT, INT};
+
+use crate::controls::Player;
+use crate::game::{
+    Creep, Game, GameError, GameEvent, Hookable, Instant, Run, Scene, ScoreWeights, SpawnerError,
+    SpawnerTweaks,
+};
+
+// Per top level run or callback. Scripts hit these with endless loops or
+// runaway recursion, which would otherwise hang the game.
+const MAX_OPERATIONS: u64 = 100_000;
+const MAX_CALL_LEVELS: usize = 32;
+const MAX_EXPR_DEPTH: usize = 64;
+const MAX_FUNCTION_EXPR_DEPTH: usize = 32;
+
+#[derive(Debug)]
+pub enum ScriptError {
+    Io(std::io::Error),
+    Parse(rhai::ParseError),
+    Run(Box<EvalAltResult>),
+    Spawner(SpawnerError),
+    Game(GameError),
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScriptError::Io(err) => write!(f, "couldn't read mode script: {}", err),
+            ScriptError::Parse(err) => write!(f, "mode script doesn't parse: {}", err),
+            ScriptError::Run(err) => write!(f, "mode script failed: {}", err),
+            ScriptError::Spawner(err) => write!(f, "mode script broke the spawner: {}", err),
+            ScriptError::Game(err) => write!(f, "mode script broke the game: {}", err),
+        }
+    }
+}
+
+// What the API functions read and write. Reads are from before the
+// callback, writes are applied to the game once it returns.
+#[derive(Default)]
+struct Shared {
+    score: INT,
+    hp: INT,
+    stage: INT,
+    tweaks: SpawnerTweaks,
+    tweaks_changed: bool,
+    // The game's own until the script changes them, so difficulty changes stick
+    weights: Option<ScoreWeights>,
+    weights_changed: bool,
+    points: INT,
+    end_run: bool,
+}
+
+pub struct ModeScript {
+    engine: Engine,
+    ast: AST,
+    // `this` in every callback, kept for the whole session
+    this: Dynamic,
+    shared: Rc<RefCell<Shared>>,
+    run_started: Option<Instant>,
+    paused_since: Option<Instant>,
+}
+
+impl ModeScript {
+    pub fn load(path: &Path, game: &mut Game) -> Result<ModeScript, ScriptError> {
+        let source = std::fs::read_to_string(path).map_err(ScriptError::Io)?;
+        ModeScript::from_source(&source, game)
+    }
+
+    pub fn from_source(source: &str, game: &mut Game) -> Result<ModeScript, ScriptError> {
+        let shared = Rc::new(RefCell::new(Shared {
+            weights: Some(game.score_weights()),
+            ..Shared::default()
+        }));
+        let mut engine = Engine::new();
+        engine
+            .set_max_operations(MAX_OPERATIONS)
+            .set_max_call_levels(MAX_CALL_LEVELS)
+            .set_max_expr_depths(MAX_EXPR_DEPTH, MAX_FUNCTION_EXPR_DEPTH);
+        register_api(&mut engine, &shared);
+        let ast = engine.compile(source).map_err(ScriptError::Parse)?;
+        engine.run_ast(&ast).map_err(ScriptError::Run)?;
+
+        let mut script = ModeScript {
+            engine,
+            ast,
+            this: Map::new().into(),
+            shared,
+            run_started: None,
+            paused_since: None,
+        };
+        script.apply(game)?;
+        Ok(script)
+    }
+
+    // Once per frame after `Game::process`, with the events it published
+    pub fn update(
+        &mut self,
+        game: &mut Game,
+        now: Instant,
+        events: &[GameEvent],
+    ) -> Result<(), ScriptError> {
+        if self.run_started.is_none() && matches!(game.scene(), Scene::Playing(Run::Solo(..))) {
+            self.run_started = Some(now);
+            self.call(game, "on_start", ())?;
+        }
+        if self.run_started.is_none() {
+            return Ok(());
+        }
+
+        for event in events {
+            match *event {
+                GameEvent::Hooked(_, kind) => self.call(game, "on_hook", (kind_name(kind),))?,
+                GameEvent::Missed(_) => self.call(game, "on_miss", ())?,
+                GameEvent::Escaped(_, kind) => self.call(game, "on_escape", (kind_name(kind),))?,
+                GameEvent::Scored(_, points) => self.call(game, "on_score", (points as INT,))?,
+                GameEvent::StageReached(stage) => self.call(game, "on_stage", (stage as INT,))?,
+                _ => {}
+            }
+        }
+
+        // Seconds of the run leave out the pause
+        if let (Some(since), false) = (self.paused_since, game.scene() == Scene::Paused) {
+            self.paused_since = None;
+            let paused = now.checked_duration_since(since);
+            self.run_started = self
+                .run_started
+                .zip(paused)
+                .map(|(started, paused)| started + paused);
+        }
+        match (game.scene(), self.run_started) {
+            (Scene::Paused, _) => {
+                self.paused_since.get_or_insert(now);
+                Ok(())
+            }
+            (Scene::Playing(Run::Solo(..)), Some(started)) => {
+                let seconds = now
+                    .checked_duration_since(started)
+                    .map(|elapsed| elapsed.to_micros() as FLOAT / 1_000_000.0)
+                    .unwrap_or_default();
+                self.call(game, "on_update", (seconds,))
+            }
+            (Scene::GameOver(Run::Solo(_, score, _, _)), _) => {
+                self.run_started = None;
+                self.call(game, "on_game_over", (score as INT,))
+            }
+            _ => {
+                self.run_started = None;
+                Ok(())
+            }
+        }
+    }
+
+    // Callbacks the script doesn't define are skipped
+    fn call<A: FuncArgs + ArgCount>(
+        &mut self,
+        game: &mut Game,
+        name: &str,
+        args: A,
+    ) -> Result<(), ScriptError> {
+        let defined = self
+            .ast
+            .iter_functions()
+            .any(|function| function.name == name && function.params.len() == A::COUNT);
+        if !defined {
+            return Ok(());
+        }
+        {
+            let mut shared = self.shared.borrow_mut();
+            shared.weights = Some(game.score_weights());
+            let run = game.scene().run();
+            shared.hp = run.and_then(|run| run.hp(Player::One)).unwrap_or_default() as INT;
+            (shared.score, shared.stage) = match run {
+                Some(Run::Solo(_, score, _, stage)) => (score as INT, stage as INT),
+                _ => (0, 0),
+            };
+        }
+        let options = CallFnOptions::new()
+            .eval_ast(false)
+            .bind_this_ptr(&mut self.this);
+        // Whatever the callback returns is ignored
+        let _: Dynamic = self
+            .engine
+            .call_fn_with_options(options, &mut Scope::new(), &self.ast, name, args)
+            .map_err(ScriptError::Run)?;
+        self.apply(game)
+    }
+
+    fn apply(&mut self, game: &mut Game) -> Result<(), ScriptError> {
+        let mut shared = self.shared.borrow_mut();
+        if std::mem::take(&mut shared.tweaks_changed) {
+            game.set_spawner_tweaks(shared.tweaks)
+                .map_err(ScriptError::Spawner)?;
+        }
+        if std::mem::take(&mut shared.weights_changed) {
+            if let Some(weights) = shared.weights {
+                game.set_score_weights(weights);
+            }
+        }
+        let points = std::mem::take(&mut shared.points);
+        if points != 0 && game.scene().is_playing() {
+            let points = points.clamp(i32::MIN as INT, i32::MAX as INT) as i32;
+            game.add_score(Player::One, points)
+                .map_err(ScriptError::Game)?;
+        }
+        if std::mem::take(&mut shared.end_run) {
+            game.end_run();
+        }
+        Ok(())
+    }
+}
+
+// Lets `call` skip functions defined with another number of parameters
+trait ArgCount {
+    const COUNT: usize;
+}
+
+impl ArgCount for () {
+    const COUNT: usize = 0;
+}
+
+impl<A> ArgCount for (A,) {
+    const COUNT: usize = 1;
+}
+
+fn kind_name(kind: Hookable) -> &'static str {
+    match kind {
+        Hookable::Creep(Creep::Radiant) => "radiant",
+        Hookable::Creep(Creep::Dire) => "dire",
+        Hookable::Blockade => "blockade",
+        Hookable::Rune(_) => "rune",
+    }
+}
+
+fn to_u32(value: INT) -> u32 {
+    value.clamp(0, u32::MAX as INT) as u32
+}
+
+fn register_api(engine: &mut Engine, shared: &Rc<RefCell<Shared>>) {
+    let api = shared.clone();
+    engine.register_fn("end_run", move || api.borrow_mut().end_run = true);
+    let api = shared.clone();
+    engine.register_fn("add_score", move |points: INT| {
+        let mut api = api.borrow_mut();
+        api.points = api.points.saturating_add(points);
+    });
+    let api = shared.clone();
+    engine.register_fn("score", move || api.borrow().score);
+    let api = shared.clone();
+    engine.register_fn("hp", move || api.borrow().hp);
+    let api = shared.clone();
+    engine.register_fn("stage", move || api.borrow().stage);
+
+    let api = shared.clone();
+    engine.register_fn("upper_lane_chance", move |chance: FLOAT| {
+        tweak(&api, |tweaks| tweaks.upper_lane_chance = Some(chance))
+    });
+    let api = shared.clone();
+    engine.register_fn("upper_lane_only", move || {
+        tweak(&api, |tweaks| tweaks.upper_lane_only = true)
+    });
+    let api = shared.clone();
+    engine.register_fn("speed_ramp", move |speed: FLOAT| {
+        tweak(&api, |tweaks| tweaks.speed_ramp = Some(speed as f32))
+    });
+    let api = shared.clone();
+    engine.register_fn("min_interval_ms", move |interval_ms: INT| {
+        tweak(&api, |tweaks| {
+            tweaks.min_interval_ms = Some(to_u32(interval_ms))
+        })
+    });
+    let api = shared.clone();
+    engine.register_fn("jitter_percent", move |percent: INT| {
+        let percent = percent.clamp(0, u8::MAX as INT) as u8;
+        tweak(&api, |tweaks| tweaks.jitter_percent = Some(percent))
+    });
+    let api = shared.clone();
+    engine.register_fn("creep_weights", move |radiant: INT, dire: INT| {
+        let weights = [radiant, dire].map(|weight| weight.clamp(0, u8::MAX as INT) as u8);
+        tweak(&api, |tweaks| tweaks.creep_weights = Some(weights))
+    });
+    let api = shared.clone();
+    engine.register_fn("max_objects", move |count: INT| {
+        tweak(&api, |tweaks| {
+            tweaks.max_objects = Some(to_u32(count) as usize)
+        })
+    });
+
+    let api = shared.clone();
+    engine.register_fn("miss_penalty", move |points: INT| {
+        weigh(&api, |weights| weights.miss_penalty = to_u32(points))
+    });
+    let api = shared.clone();
+    engine.register_fn("long_hook_bonus", move |points: INT| {
+        weigh(&api, |weights| weights.long_hook_bonus = to_u32(points))
+    });
+    let api = shared.clone();
+    engine.register_fn("fastest_creep_bonus", move |points: INT| {
+        weigh(&api, |weights| weights.fastest_creep_bonus = to_u32(points))
+    });
+}
+
+fn tweak(shared: &Rc<RefCell<Shared>>, change: impl FnOnce(&mut SpawnerTweaks)) {
+    let mut shared = shared.borrow_mut();
+    change(&mut shared.tweaks);
+    shared.tweaks_changed = true;
+}
+
+fn weigh(shared: &Rc<RefCell<Shared>>, change: impl FnOnce(&mut ScoreWeights)) {
+    let mut shared = shared.borrow_mut();
+    if let Some(weights) = shared.weights.as_mut() {
+        change(weights);
+        shared.weights_changed = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controls::ControlEnum;
+    use crate::game::testing::{new_game, tick_clock};
+    use crate::game::{Bot, GameDifficultyEnum, Mode, TICK};
+
+    // Plays on from `tick` until `done`
+    fn play(
+        game: &mut Game,
+        script: &mut ModeScript,
+        tick: &mut u64,
+        control: impl Fn(&Game) -> ControlEnum,
+        mut done: impl FnMut(&Game) -> bool,
+    ) {
+        let give_up = *tick + 100_000;
+        while !done(game) {
+            assert!(*tick < give_up, "never got there");
+            *tick += 1;
+            game.control(Player::One, control(game));
+            game.process(tick_clock(*tick)).unwrap();
+            let events: Vec<GameEvent> = game.drain_events().collect();
+            script.update(game, tick_clock(*tick), &events).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_no_miss_ends_the_run() {
+        let mut game = new_game();
+        let mut script =
+            ModeScript::from_source(include_str!("../assets/modes/no_miss.rhai"), &mut game)
+                .unwrap();
+        assert_eq!(game.score_weights().long_hook_bonus, 100);
+
+        let mut tick = 0;
+        game.start_run(Mode::Classic);
+        // Nothing spawns during the stage banner, a hook right away misses
+        play(
+            &mut game,
+            &mut script,
+            &mut tick,
+            |_| ControlEnum::Hook,
+            |game| !game.scene().is_playing(),
+        );
+        assert!(matches!(
+            game.scene(),
+            Scene::GameOver(Run::Solo(_, _, _, 1))
+        ));
+    }
+
+    #[test]
+    fn test_time_attack_ends_after_a_minute() {
+        let mut game = new_game();
+        let mut script =
+            ModeScript::from_source(include_str!("../assets/modes/time_attack.rhai"), &mut game)
+                .unwrap();
+        let bot = Bot::new(Player::One);
+        let mut tick = 0;
+        game.start_run(Mode::Classic);
+        let started = tick;
+        play(
+            &mut game,
+            &mut script,
+            &mut tick,
+            |game| bot.control(game),
+            |game| !game.scene().is_playing(),
+        );
+        let seconds = (tick - started) * TICK.to_micros() / 1_000_000;
+        assert_eq!(seconds, 60);
+        assert!(matches!(game.scene(), Scene::GameOver(Run::Solo(_, score, _, _)) if score > 0));
+    }
+
+    #[test]
+    fn test_callbacks_keep_state_and_tweak_the_game() {
+        let mut game = new_game();
+        let source = r#"
+            upper_lane_only();
+            fn on_start() { this.hooks = 0; }
+            fn on_hook(kind) {
+                this.hooks += 1;
+                if this.hooks == 2 { add_score(100000); }
+            }
+            fn on_hook() { throw "called with the wrong arguments"; }
+        "#;
+        let mut script = ModeScript::from_source(source, &mut game).unwrap();
+        let bot = Bot::new(Player::One);
+        let mut scores = Vec::new();
+        game.start_run(Mode::Classic);
+        play(
+            &mut game,
+            &mut script,
+            &mut 0,
+            |game| bot.control(game),
+            |game| {
+                if let Scene::Playing(Run::Solo(_, score, _, _)) = game.scene() {
+                    scores.push(score);
+                }
+                scores.len() > 2000
+            },
+        );
+        // The bonus shows up as a jump no single hook gets near
+        assert!(scores.windows(2).any(|pair| pair[1] - pair[0] >= 100000));
+        let this = script.this.clone_cast::<Map>();
+        assert!(this["hooks"].as_int().is_ok_and(|hooks| hooks >= 2));
+    }
+
+    #[test]
+    fn test_runaway_scripts_are_stopped() {
+        let mut game = new_game();
+        let endless = ModeScript::from_source("loop {}", &mut game);
+        assert!(matches!(endless, Err(ScriptError::Run(_))));
+
+        let source = "fn deeper(n) { deeper(n + 1) } fn on_start() { deeper(0) }";
+        let mut script = ModeScript::from_source(source, &mut game).unwrap();
+        game.start_run(Mode::Classic);
+        game.process(tick_clock(1)).unwrap();
+        let err = script.update(&mut game, tick_clock(1), &[]);
+        assert!(matches!(err, Err(ScriptError::Run(_))));
+    }
+
+    #[test]
+    fn test_difficulty_weights_survive_callbacks() {
+        let mut game = new_game();
+        let source = "fn on_start() {} fn on_miss() { miss_penalty(7); }";
+        let mut script = ModeScript::from_source(source, &mut game).unwrap();
+
+        game.set_difficulty(GameDifficultyEnum::Hard);
+        game.start_run(Mode::Classic);
+        game.process(tick_clock(1)).unwrap();
+        script.update(&mut game, tick_clock(1), &[]).unwrap();
+        let hard = ScoreWeights::for_difficulty(GameDifficultyEnum::Hard);
+        assert_eq!(game.score_weights(), hard);
+
+        // Scoring functions change the weights in effect, not the ones from loading
+        let missed = GameEvent::Missed(Player::One);
+        script.update(&mut game, tick_clock(1), &[missed]).unwrap();
+        assert_eq!(
+            game.score_weights(),
+            ScoreWeights {
+                miss_penalty: 7,
+                ..hard
+            }
+        );
+    }
+}
@@ -11,6 +11,7 @@ use std::{
 use embedded_graphics::{image::Image, prelude::Point};
 use once_cell::sync::Lazy;
 use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
 use wasm_timer::SystemTime;
 use web_sys::{console::log_1, MouseEvent, WheelEvent};
 
@@ -39,16 +40,18 @@ macro_rules! console_log {
 }
 
 use crate::{
-    controls::ControlEnum,
+    controls::{ControlEnum, Player},
     game::Game,
     graphics::{
         display::{clear_display, get_display, webview::flush},
         image::draw_image,
         resources::SPLASH,
+        sprite_pack::SpritePack,
     },
 };
 
 use crate::game::Instant as GameInstant;
+use crate::net::{wasm::WasmLink, Session};
 
 const NUM_ITER: i32 = 1;
 
@@ -99,6 +102,7 @@ pub fn wasm_main() {
     #[cfg(debug_assertions)]
     console_error_panic_hook::set_once();
     println!("Starting wasm");
+    load_sprite_pack();
 
     unsafe {
         MOUSE = Some(Arc::new(Mutex::new(WebMouse {
@@ -168,6 +172,7 @@ pub fn wasm_main() {
     let mouse_window_ref = unsafe { MOUSE.clone().unwrap() };
     let mut game = Game::default();
     game.init();
+    let mut session = connect_relay();
 
     *graphics_ref.borrow_mut() = Some(Closure::wrap(Box::new(move || {
         let ticks: u64 = (now() as u64) * 1000;
@@ -198,8 +203,33 @@ pub fn wasm_main() {
         game.draw(&mut display);
 
         flush(&mut display);
-        game.process(game_instant);
-        game.control(control);
+        if let Some(ref mut online) = session {
+            if control.is_some() {
+                online.control(&mut game, control);
+            }
+            let local = online.local_player();
+            // Nothing here reads the events, they're dropped every tick so
+            // the queue only overflows for real
+            let discard = |game: &mut Game| {
+                let _ = game.drain_events();
+            };
+            if let Err(err) = online.update(&mut game, game_instant, discard) {
+                console_log!(
+                    "Online match over after {} ticks, {}",
+                    online.tick().unwrap_or_default(),
+                    err
+                );
+                session = None;
+            } else if local.is_none() && online.local_player().is_some() {
+                console_log!("Match started as {:?}", online.local_player().unwrap());
+            }
+        } else {
+            if let Err(err) = game.process(game_instant) {
+                console_log!("Skipped tick, {}", err);
+            }
+            game.control(Player::One, control);
+        }
+        let _ = game.drain_events();
 
         // Schedule ourself for another requestAnimationFrame callback.
         set_timeout(graphics_anchor.borrow().as_ref().unwrap(), LOGIC_TIMEOUT);
@@ -207,6 +237,57 @@ pub fn wasm_main() {
 
     set_timeout(graphics_ref.borrow().as_ref().unwrap(), LOGIC_TIMEOUT);
 }
+// `?relay=<ws url>` plays versus against whoever else joins that relay
+fn connect_relay() -> Option<Session<WasmLink>> {
+    let search = window().location().search().ok()?;
+    let params = web_sys::UrlSearchParams::new_with_str(&search).ok()?;
+    let url = params.get("relay")?;
+    match WasmLink::connect(&url) {
+        Ok(link) => {
+            console_log!("Connecting to {}, waiting for a second player", url);
+            let pace = fugit::MicrosDurationU64::millis(LOGIC_TIMEOUT as u64);
+            Some(Session::new(link, pace))
+        }
+        Err(_) => {
+            console_log!("Playing offline, couldn't open {}", url);
+            None
+        }
+    }
+}
+
+// `?sprites=<zip url>` swaps the built-in sprites once the pack is fetched
+fn load_sprite_pack() {
+    let Ok(search) = window().location().search() else {
+        return;
+    };
+    let Ok(params) = web_sys::UrlSearchParams::new_with_str(&search) else {
+        return;
+    };
+    let Some(url) = params.get("sprites") else {
+        return;
+    };
+    wasm_bindgen_futures::spawn_local(async move {
+        match fetch_bytes(&url).await {
+            Ok(data) => match SpritePack::from_zip(&data).and_then(|pack| pack.install()) {
+                Ok(()) => console_log!("Loaded sprite pack {}", url),
+                Err(err) => console_log!("Using built-in sprites, {}", err),
+            },
+            Err(_) => console_log!("Couldn't fetch sprite pack {}", url),
+        }
+    });
+}
+
+async fn fetch_bytes(url: &str) -> Result<Vec<u8>, JsValue> {
+    let response: web_sys::Response = JsFuture::from(window().fetch_with_str(url))
+        .await?
+        .dyn_into()?;
+    if !response.ok() {
+        return Err(JsValue::from_str("unexpected response status"));
+    }
+    let buffer = JsFuture::from(response.array_buffer()?).await?;
+    Ok(js_sys::Uint8Array::new(&buffer).to_vec())
+}
+
 fn set_timeout(f: &Closure<dyn FnMut()>, timeout_ms: i32) -> i32 {
     window()
         .set_timeout_with_callback_and_timeout_and_arguments_0(